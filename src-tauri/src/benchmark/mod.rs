@@ -0,0 +1,195 @@
+pub mod runner;
+pub mod suite;
+
+use crate::hardware;
+use crate::llm::{ModelInfo, PerformanceMetrics};
+use crate::settings::paths;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const BENCHMARKS_FILE: &str = "benchmarks.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptResult {
+    pub name: String,
+    pub time_to_first_token_ms: f64,
+    pub tokens_generated: u32,
+    /// Decode speed, excluding the time to the first token
+    pub tokens_per_second: f64,
+    pub prompt_tokens_per_second: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub model: String,
+    pub provider_id: String,
+    /// Identifies the exact weights when the provider reports it
+    pub digest: Option<String>,
+    pub hardware_profile: String,
+    pub suite_version: u32,
+    pub prompts: Vec<PromptResult>,
+    pub metrics: PerformanceMetrics,
+}
+
+impl BenchmarkResult {
+    fn matches(&self, model: &ModelInfo, hardware_profile: &str) -> bool {
+        let same_model = match &model.metadata.digest {
+            Some(digest) => self.digest.as_ref() == Some(digest),
+            // Without a digest, the best we can do is the name on that provider
+            None => self.digest.is_none() && self.model == model.id && self.provider_id == model.provider_id,
+        };
+        same_model && self.hardware_profile == hardware_profile && self.suite_version == suite::SUITE_VERSION
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BenchmarkFile {
+    results: Vec<BenchmarkResult>,
+}
+
+/// Benchmark results persisted as JSON in the app data dir, one per model
+/// and hardware profile.
+pub struct BenchmarkStore {
+    path: Option<PathBuf>,
+    results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkStore {
+    pub fn load() -> Self {
+        let path = paths::app_data_dir().map(|dir| dir.join(BENCHMARKS_FILE));
+
+        let results = match &path {
+            Some(path) => read_results(path).unwrap_or_else(|e| {
+                tracing::error!("Failed to load benchmarks from {}: {:#}", path.display(), e);
+                vec![]
+            }),
+            None => vec![],
+        };
+
+        Self { path, results }
+    }
+
+    /// Stores a result, replacing any earlier run of the same model on the
+    /// same hardware.
+    pub fn record(&mut self, result: BenchmarkResult) -> Result<()> {
+        self.results.retain(|existing| {
+            !(existing.hardware_profile == result.hardware_profile
+                && existing.model == result.model
+                && existing.provider_id == result.provider_id
+                && existing.digest == result.digest)
+        });
+        self.results.push(result);
+
+        if let Some(path) = &self.path {
+            write_results(path, &self.results)?;
+        }
+        Ok(())
+    }
+
+    /// Fills in `performance` for models benchmarked on this machine with
+    /// the current suite.
+    pub fn fill_performance(&self, models: &mut [ModelInfo]) {
+        let profile = hardware_profile();
+        for model in models {
+            if let Some(result) = self.results.iter().rev().find(|r| r.matches(model, profile)) {
+                model.performance = Some(result.metrics.clone());
+            }
+        }
+    }
+}
+
+/// Describes the machine well enough that results from different hardware
+/// are kept apart. Detected once per run since it doesn't change.
+pub fn hardware_profile() -> &'static str {
+    static PROFILE: OnceLock<String> = OnceLock::new();
+    PROFILE.get_or_init(|| {
+        let info = hardware::detect_hardware();
+        format!(
+            "{} / {} GB RAM / {} / {} GB VRAM",
+            info.cpu,
+            info.ram,
+            info.gpu.as_deref().unwrap_or("no GPU"),
+            info.vram
+        )
+    })
+}
+
+fn read_results(path: &Path) -> Result<Vec<BenchmarkResult>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let file: BenchmarkFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(file.results)
+}
+
+fn write_results(path: &Path, results: &[BenchmarkResult]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let file = BenchmarkFile { results: results.to_vec() };
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_string_pretty(&file)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LLMProvider, ModelMetadata, ModelStatus};
+
+    fn model(id: &str, digest: Option<&str>) -> ModelInfo {
+        ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            size: None,
+            provider: LLMProvider::Ollama,
+            provider_id: "ollama".to_string(),
+            status: ModelStatus::Loaded,
+            performance: None,
+            context_length: None,
+            quantization: None,
+            metadata: ModelMetadata { digest: digest.map(str::to_string), ..ModelMetadata::default() },
+        }
+    }
+
+    fn result(model: &str, digest: Option<&str>, suite_version: u32) -> BenchmarkResult {
+        BenchmarkResult {
+            model: model.to_string(),
+            provider_id: "ollama".to_string(),
+            digest: digest.map(str::to_string),
+            hardware_profile: hardware_profile().to_string(),
+            suite_version,
+            prompts: vec![],
+            metrics: PerformanceMetrics {
+                tokens_per_second: 42.0,
+                time_to_first_token: Some(180.0),
+                prompt_tokens_per_second: None,
+                memory_usage: None,
+                last_updated: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_performance_follows_digest_and_suite_version() {
+        let mut store = BenchmarkStore { path: None, results: vec![] };
+        store.record(result("llama3.1:8b", Some("sha256:aaa"), suite::SUITE_VERSION)).unwrap();
+        store.record(result("qwen2.5-coder:7b", Some("sha256:bbb"), suite::SUITE_VERSION - 1)).unwrap();
+
+        let mut models = vec![
+            model("llama3.1:8b", Some("sha256:aaa")),
+            // Re-pulled under the same name, so the old numbers don't apply
+            model("llama3.1:8b", Some("sha256:ccc")),
+            model("qwen2.5-coder:7b", Some("sha256:bbb")),
+        ];
+        store.fill_performance(&mut models);
+
+        assert_eq!(models[0].performance.as_ref().map(|p| p.tokens_per_second), Some(42.0));
+        assert!(models[1].performance.is_none());
+        assert!(models[2].performance.is_none());
+    }
+}
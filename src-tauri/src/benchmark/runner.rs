@@ -0,0 +1,258 @@
+use super::suite::{self, PROMPTS};
+use super::{hardware_profile, BenchmarkResult, PromptResult};
+use crate::llm::{GenerateRequest, LLMClient, LLMError, LLMProvider, LLMResult, PerformanceMetrics, SamplingOptions, TokenCallback};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessesToUpdate, System};
+
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs the benchmark suite against `model` on a single provider, without
+/// falling back, so the numbers describe exactly that server.
+pub async fn run_benchmark(client: Arc<dyn LLMClient>, provider_id: &str, model: &str) -> LLMResult<BenchmarkResult> {
+    let info = client.get_model_info(model).await?;
+
+    // Load the model first so the first prompt's TTFT doesn't include it
+    client.generate(request(model, "Hi", 1)).await?;
+
+    let sampler = MemorySampler::start(client.provider(), client.base_url());
+    let mut prompts = vec![];
+    for benchmark_prompt in PROMPTS {
+        prompts.push(run_prompt(client.as_ref(), model, benchmark_prompt).await?);
+    }
+    let memory_usage = sampler.and_then(MemorySampler::finish);
+
+    let count = prompts.len() as f64;
+    let mean = |value: fn(&PromptResult) -> f64| prompts.iter().map(value).sum::<f64>() / count;
+    let prompt_rates: Vec<f64> = prompts.iter().filter_map(|p| p.prompt_tokens_per_second).collect();
+
+    let metrics = PerformanceMetrics {
+        tokens_per_second: mean(|p| p.tokens_per_second),
+        time_to_first_token: Some(mean(|p| p.time_to_first_token_ms)),
+        prompt_tokens_per_second: (!prompt_rates.is_empty())
+            .then(|| prompt_rates.iter().sum::<f64>() / prompt_rates.len() as f64),
+        memory_usage,
+        last_updated: chrono::Utc::now().to_rfc3339(),
+    };
+
+    Ok(BenchmarkResult {
+        model: model.to_string(),
+        provider_id: provider_id.to_string(),
+        digest: info.metadata.digest,
+        hardware_profile: hardware_profile().to_string(),
+        suite_version: suite::SUITE_VERSION,
+        prompts,
+        metrics,
+    })
+}
+
+async fn run_prompt(client: &dyn LLMClient, model: &str, benchmark_prompt: &suite::BenchmarkPrompt) -> LLMResult<PromptResult> {
+    let first_token = Arc::new(Mutex::new(None));
+    let started = Instant::now();
+
+    let on_token: TokenCallback = {
+        let first_token = first_token.clone();
+        Arc::new(move |_: &str| {
+            first_token.lock().unwrap().get_or_insert_with(|| started.elapsed());
+        })
+    };
+
+    let mut stream_request = request(model, benchmark_prompt.prompt, suite::MAX_TOKENS);
+    stream_request.stream = true;
+    let response = client.generate_stream(stream_request, on_token).await?;
+    let total = started.elapsed();
+
+    let time_to_first_token = first_token.lock().unwrap()
+        .ok_or_else(|| LLMError::Provider {
+            message: format!("{} produced no tokens for benchmark prompt {}", model, benchmark_prompt.name),
+        })?;
+
+    // Prefer the provider's own timings; otherwise fall back to what we
+    // measured around the stream
+    let timings = &response.timings;
+    let ttft_ms = timings.time_to_first_token_ms.unwrap_or(time_to_first_token.as_secs_f64() * 1000.0);
+    let tokens_per_second = if timings.provider_reported {
+        response.tokens_per_second
+    } else {
+        let decode_secs = (total - time_to_first_token).as_secs_f64();
+        if decode_secs > 0.0 {
+            response.tokens_generated.saturating_sub(1) as f64 / decode_secs
+        } else {
+            0.0
+        }
+    };
+
+    let prompt_tokens_per_second = timings.prompt_tokens_per_second().or_else(|| {
+        // Rough token count; enough to compare prompt processing between models
+        let estimated_prompt_tokens = benchmark_prompt.prompt.len() as f64 / 4.0;
+        let ttft_secs = time_to_first_token.as_secs_f64();
+        (ttft_secs > 0.0).then(|| estimated_prompt_tokens / ttft_secs)
+    });
+
+    Ok(PromptResult {
+        name: benchmark_prompt.name.to_string(),
+        time_to_first_token_ms: ttft_ms,
+        tokens_generated: response.tokens_generated,
+        tokens_per_second,
+        prompt_tokens_per_second,
+    })
+}
+
+fn request(model: &str, prompt: &str, max_tokens: i32) -> GenerateRequest {
+    GenerateRequest {
+        model: model.to_string(),
+        prompt: prompt.to_string(),
+        sampling: SamplingOptions {
+            temperature: Some(suite::TEMPERATURE),
+            max_tokens: Some(max_tokens),
+            ..SamplingOptions::default()
+        },
+        schema: None,
+        stream: false,
+        schedule: None,
+    }
+}
+
+// Tracks the peak resident memory of the local server process while the
+// suite runs. Stops sampling when dropped, including on cancellation.
+struct MemorySampler {
+    stop: Arc<AtomicBool>,
+    peak: Arc<Mutex<Option<u64>>>,
+}
+
+impl MemorySampler {
+    fn start(provider: LLMProvider, base_url: &str) -> Option<Self> {
+        let names = server_process_names(provider);
+        if names.is_empty() || !is_local(base_url) {
+            return None;
+        }
+
+        let sampler = Self {
+            stop: Arc::new(AtomicBool::new(false)),
+            peak: Arc::new(Mutex::new(None)),
+        };
+
+        let stop = sampler.stop.clone();
+        let peak = sampler.peak.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut system = System::new();
+            while !stop.load(Ordering::SeqCst) {
+                system.refresh_processes(ProcessesToUpdate::All);
+                let used: u64 = system.processes().values()
+                    .filter(|p| {
+                        let name = p.name().to_string_lossy().to_lowercase();
+                        names.iter().any(|n| name.contains(n))
+                    })
+                    .map(|p| p.memory())
+                    .sum();
+
+                if used > 0 {
+                    let mut peak = peak.lock().unwrap();
+                    *peak = Some(peak.map_or(used, |p| p.max(used)));
+                }
+                std::thread::sleep(MEMORY_SAMPLE_INTERVAL);
+            }
+        });
+
+        Some(sampler)
+    }
+
+    fn finish(self) -> Option<u64> {
+        *self.peak.lock().unwrap()
+    }
+}
+
+impl Drop for MemorySampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+fn server_process_names(provider: LLMProvider) -> &'static [&'static str] {
+    match provider {
+        // Includes the runner subprocesses that hold the weights
+        LLMProvider::Ollama => &["ollama"],
+        LLMProvider::LMStudio => &["lm studio", "lmstudio", "lm-studio", "llmworker"],
+        LLMProvider::OpenAI => &[],
+    }
+}
+
+fn is_local(base_url: &str) -> bool {
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| matches!(host, "localhost" | "127.0.0.1" | "[::1]" | "0.0.0.0")))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::BenchmarkStore;
+    use crate::llm::ollama::OllamaClient;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Answers /api/tags, /api/show and /api/generate for a single model
+    async fn mock_ollama() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut request = vec![];
+                    let mut buf = [0u8; 4096];
+                    let (head, body) = loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head.lines()
+                                .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                                .unwrap_or(0);
+                            if body.len() >= length || n == 0 {
+                                break (head.to_string(), body.to_string());
+                            }
+                        }
+                    };
+
+                    let path = head.split_whitespace().nth(1).unwrap_or_default();
+                    let done = r#"{"model":"llama3.1:8b","response":"","done":true,"done_reason":"stop","prompt_eval_count":12,"prompt_eval_duration":20000000,"eval_count":3,"eval_duration":60000000}"#;
+                    let response_body = match path {
+                        "/api/tags" => r#"{"models":[{"name":"llama3.1:8b","size":4920753328,"digest":"sha256:aaa","details":{}}]}"#.to_string(),
+                        "/api/show" => r#"{"details":{"family":"llama"},"model_info":{},"capabilities":["completion"]}"#.to_string(),
+                        _ if body.contains(r#""stream":true"#) => {
+                            format!("{}\n{}\n", r#"{"model":"llama3.1:8b","response":"const","done":false}"#, done)
+                        }
+                        _ => done.to_string(),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        response_body.len(),
+                        response_body,
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn test_ollama_benchmark_fills_in_listed_model_performance() {
+        let client = Arc::new(OllamaClient::new(mock_ollama().await));
+        let result = run_benchmark(client.clone(), "ollama", "llama3.1:8b").await.unwrap();
+        assert_eq!(result.digest.as_deref(), Some("sha256:aaa"));
+
+        let mut store = BenchmarkStore { path: None, results: vec![] };
+        store.record(result).unwrap();
+
+        let mut models = client.list_models().await.unwrap();
+        for model in &mut models {
+            model.provider_id = "ollama".to_string();
+        }
+        store.fill_performance(&mut models);
+        assert!(models[0].performance.is_some());
+    }
+}
@@ -0,0 +1,36 @@
+// Bump whenever a prompt or a setting below changes, so results measured
+// with a different suite are never compared against each other
+pub const SUITE_VERSION: u32 = 1;
+
+// Fixed output length and greedy sampling keep runs comparable
+pub const MAX_TOKENS: i32 = 256;
+pub const TEMPERATURE: f32 = 0.0;
+
+pub struct BenchmarkPrompt {
+    pub name: &'static str,
+    pub prompt: &'static str,
+}
+
+pub const PROMPTS: &[BenchmarkPrompt] = &[
+    BenchmarkPrompt {
+        name: "button",
+        prompt: "Create a React button component in TypeScript with Tailwind CSS. \
+                 It should support primary, secondary and danger variants, a loading \
+                 state with a spinner, and forward all native button props. \
+                 Return only the code.",
+    },
+    BenchmarkPrompt {
+        name: "form",
+        prompt: "Create a React sign-up form component in TypeScript with Tailwind CSS. \
+                 It needs name, email and password fields, inline validation messages, \
+                 a password strength meter, and a submit handler passed in as a prop. \
+                 Return only the code.",
+    },
+    BenchmarkPrompt {
+        name: "data-table",
+        prompt: "Create a React data table component in TypeScript with Tailwind CSS. \
+                 It takes generic rows and column definitions, supports sorting by \
+                 clicking a header, client-side pagination, and an empty state. \
+                 Return only the code.",
+    },
+];
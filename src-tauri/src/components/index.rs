@@ -0,0 +1,351 @@
+use super::SavedComponent;
+use crate::llm::{LLMError, LLMResult, LLMRouter};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const INDEX_VERSION: u32 = 1;
+
+// Share of a blended score that comes from embedding similarity; the rest
+// is keyword matching, which catches exact names the embedding may not
+const SEMANTIC_WEIGHT: f32 = 0.7;
+
+// Embedding models have short context windows, and the start of a
+// component (imports, props, signature) says the most about it
+const MAX_CODE_CHARS: usize = 4000;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    provider_id: String,
+    model: String,
+    dimensions: usize,
+    entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    id: String,
+    // Hash of the embedded text, to tell when a component has changed
+    content_hash: String,
+    embedding: Vec<f32>,
+}
+
+/// What a sync changed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexUpdate {
+    pub embedded: usize,
+    pub removed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentMatch {
+    pub component: SavedComponent,
+    pub score: f32,
+    /// Cosine similarity to the query; None when searching by keywords only
+    pub semantic_score: Option<f32>,
+    pub keyword_score: f32,
+}
+
+/// Embeddings of the saved components, persisted so that only new and
+/// changed components are embedded again. Vectors are only comparable
+/// within one model, so switching embedding model rebuilds the index.
+pub struct ComponentIndex {
+    path: Option<PathBuf>,
+    file: IndexFile,
+}
+
+impl ComponentIndex {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let file = match &path {
+            Some(path) => read_index(path).unwrap_or_else(|e| {
+                tracing::error!("Failed to load component index from {}: {:#}", path.display(), e);
+                IndexFile::default()
+            }),
+            None => IndexFile::default(),
+        };
+        Self { path, file }
+    }
+
+    /// Embeds components that are new or changed since the last sync and
+    /// drops ones that were deleted.
+    pub async fn sync(
+        &mut self,
+        router: &LLMRouter,
+        provider: &str,
+        model: &str,
+        components: &[SavedComponent],
+    ) -> LLMResult<IndexUpdate> {
+        if self.file.version != INDEX_VERSION || self.file.provider_id != provider || self.file.model != model {
+            self.file = IndexFile {
+                version: INDEX_VERSION,
+                provider_id: provider.to_string(),
+                model: model.to_string(),
+                ..IndexFile::default()
+            };
+        }
+
+        let mut entries: HashMap<String, IndexEntry> = self.file.entries.drain(..)
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+        let live: HashSet<&str> = components.iter().map(|c| c.id.as_str()).collect();
+        let before = entries.len();
+        entries.retain(|id, _| live.contains(id.as_str()));
+        let removed = before - entries.len();
+
+        let mut stale_ids = vec![];
+        let mut stale_texts = vec![];
+        let mut stale_hashes = vec![];
+        for component in components {
+            let text = index_text(component);
+            let hash = content_hash(&text);
+            let unchanged = matches!(entries.get(&component.id), Some(entry) if entry.content_hash == hash);
+            if !unchanged {
+                stale_ids.push(component.id.clone());
+                stale_texts.push(text);
+                stale_hashes.push(hash);
+            }
+        }
+
+        let embedded = stale_ids.len();
+        let result = match embedded {
+            0 => Ok(()),
+            _ => router.embed(provider, model, &stale_texts).await.and_then(|response| {
+                if response.embeddings.len() != embedded {
+                    return Err(LLMError::Decode {
+                        message: format!("Expected {} embeddings, got {}", embedded, response.embeddings.len()),
+                    });
+                }
+                self.file.dimensions = response.dimensions;
+                for ((id, content_hash), embedding) in stale_ids.into_iter().zip(stale_hashes).zip(response.embeddings) {
+                    entries.insert(id.clone(), IndexEntry { id, content_hash, embedding });
+                }
+                Ok(())
+            }),
+        };
+
+        // Keep what's still valid even if embedding failed
+        self.file.entries = components.iter().filter_map(|c| entries.remove(&c.id)).collect();
+        result?;
+
+        if embedded > 0 || removed > 0 {
+            self.save().map_err(|e| LLMError::Internal { message: format!("Failed to save component index: {:#}", e) })?;
+        }
+        Ok(IndexUpdate { embedded, removed, total: self.file.entries.len() })
+    }
+
+    /// Syncs the index, then ranks components against `query` by meaning
+    /// and keywords.
+    pub async fn query(
+        &mut self,
+        router: &LLMRouter,
+        provider: &str,
+        model: &str,
+        components: Vec<SavedComponent>,
+        query: &str,
+        limit: usize,
+    ) -> LLMResult<Vec<ComponentMatch>> {
+        self.sync(router, provider, model, &components).await?;
+        let response = router.embed(provider, model, &[query.to_string()]).await?;
+        Ok(self.search(query, response.embeddings.first().map(Vec::as_slice), components, limit))
+    }
+
+    /// Ranks components against `query`. Without a query embedding only
+    /// keywords count, and components matching none are left out.
+    pub fn search(
+        &self,
+        query: &str,
+        query_embedding: Option<&[f32]>,
+        components: Vec<SavedComponent>,
+        limit: usize,
+    ) -> Vec<ComponentMatch> {
+        let terms: Vec<String> = query.to_lowercase().split_whitespace().map(str::to_string).collect();
+        let embeddings: HashMap<&str, &[f32]> = self.file.entries.iter()
+            .map(|entry| (entry.id.as_str(), entry.embedding.as_slice()))
+            .collect();
+
+        let mut matches: Vec<ComponentMatch> = components.into_iter()
+            .filter_map(|component| {
+                let keyword_score = keyword_score(&terms, &component);
+                let semantic_score = query_embedding
+                    .map(|query| embeddings.get(component.id.as_str()).map_or(0.0, |e| cosine(query, e)));
+                let score = match semantic_score {
+                    Some(semantic) => SEMANTIC_WEIGHT * semantic + (1.0 - SEMANTIC_WEIGHT) * keyword_score,
+                    None if keyword_score > 0.0 => keyword_score,
+                    None => return None,
+                };
+                Some(ComponentMatch { component, score, semantic_score, keyword_score })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        matches
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => write_index(path, &self.file),
+            None => Ok(()),
+        }
+    }
+}
+
+// What gets embedded: the component's name, description and tags, its
+// props type, and the start of its code
+fn index_text(component: &SavedComponent) -> String {
+    let code_end = component.code.char_indices().nth(MAX_CODE_CHARS).map_or(component.code.len(), |(i, _)| i);
+    format!(
+        "{}\n{}\nTags: {}\n{}\n{}",
+        component.name,
+        component.description,
+        component.tags.join(", "),
+        props_type(&component.code).unwrap_or_default(),
+        &component.code[..code_end],
+    )
+}
+
+// The first `interface ...Props { }` or `type ...Props = { }` declaration,
+// which describes a component's API better than its body does
+fn props_type(code: &str) -> Option<&str> {
+    for keyword in ["interface ", "type "] {
+        for (start, _) in code.match_indices(keyword) {
+            let declaration = &code[start..];
+            let Some(open) = declaration.find('{') else { continue };
+            if !declaration[..open].contains("Props") || declaration[..open].contains('\n') {
+                continue;
+            }
+
+            let mut depth = 0;
+            for (i, c) in declaration[open..].char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    return Some(&declaration[..=open + i]);
+                }
+            }
+        }
+    }
+    None
+}
+
+// Share of query terms found in the component, with terms in the name
+// counting most and terms only in the code least
+fn keyword_score(terms: &[String], component: &SavedComponent) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let name = component.name.to_lowercase();
+    let details = format!("{} {}", component.description, component.tags.join(" ")).to_lowercase();
+    let code = component.code.to_lowercase();
+    let total: f32 = terms.iter()
+        .map(|term| {
+            if name.contains(term.as_str()) {
+                1.0
+            } else if details.contains(term.as_str()) {
+                0.7
+            } else if code.contains(term.as_str()) {
+                0.3
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    total / terms.len() as f32
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match norm(a) * norm(b) {
+        n if n > 0.0 => dot / n,
+        _ => 0.0,
+    }
+}
+
+fn content_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn read_index(path: &Path) -> Result<IndexFile> {
+    if !path.exists() {
+        return Ok(IndexFile::default());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn write_index(path: &Path, file: &IndexFile) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_string(file)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(id: &str, name: &str, description: &str, code: &str) -> SavedComponent {
+        SavedComponent {
+            id: id.to_string(),
+            name: name.to_string(),
+            file_name: format!("{}.tsx", name),
+            description: description.to_string(),
+            code: code.to_string(),
+            timestamp: String::new(),
+            tags: vec![],
+            crystal: "amethyst".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_search_blends_semantic_and_keyword_scores() {
+        let components = vec![
+            component("a", "LoginForm", "Email and password sign-in", "export function LoginForm() {}"),
+            component("b", "PricingCard", "Plan with price and features", "export function PricingCard() {}"),
+        ];
+        let index = ComponentIndex {
+            path: None,
+            file: IndexFile {
+                entries: vec![
+                    IndexEntry { id: "a".to_string(), content_hash: String::new(), embedding: vec![1.0, 0.0] },
+                    IndexEntry { id: "b".to_string(), content_hash: String::new(), embedding: vec![0.0, 1.0] },
+                ],
+                ..IndexFile::default()
+            },
+        };
+
+        // Keywords alone only find components that mention the terms
+        let keyword = index.search("pricing", None, components.clone(), 10);
+        assert_eq!(keyword.len(), 1);
+        assert_eq!(keyword[0].component.id, "b");
+
+        // A query close in meaning to the login form ranks it first even
+        // without shared words
+        let semantic = index.search("authentication screen", Some(&[0.9, 0.1]), components, 10);
+        assert_eq!(semantic[0].component.id, "a");
+        assert!(semantic[0].semantic_score.unwrap() > 0.9);
+    }
+
+    #[test]
+    fn test_props_type_is_extracted() {
+        let code = "import React from 'react';\n\ninterface ButtonProps {\n  label: string;\n  style?: { color: string };\n}\n\nexport function Button({ label }: ButtonProps) {}";
+        assert_eq!(props_type(code), Some("interface ButtonProps {\n  label: string;\n  style?: { color: string };\n}"));
+        assert_eq!(props_type("const x = 1;"), None);
+        assert_eq!(props_type("interface CaféProps { a: { b: 1 } }"), Some("interface CaféProps { a: { b: 1 } }"));
+    }
+}
@@ -0,0 +1,124 @@
+pub mod index;
+pub mod tools;
+
+use crate::settings::paths;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Match COMPONENTS_DIR and MANIFEST_FILE in src/services/componentManager.ts,
+// which writes these through the fs plugin's AppData base dir
+const COMPONENTS_DIR: &str = "crystal-forge-components";
+const MANIFEST_FILE: &str = "components-manifest.json";
+const DESIGN_TOKENS_FILE: &str = "design-tokens.json";
+// Written by the backend only
+const INDEX_FILE: &str = "component-index.json";
+
+// Generated components are small; anything bigger isn't one of ours
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+/// A component saved by the frontend's ComponentManager.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedComponent {
+    pub id: String,
+    pub name: String,
+    pub file_name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub timestamp: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub crystal: String,
+}
+
+/// Read-only access to the saved components directory. Files are looked up
+/// by bare name and never resolved outside the directory.
+#[derive(Debug, Clone)]
+pub struct ComponentLibrary {
+    dir: PathBuf,
+}
+
+impl ComponentLibrary {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn open() -> Option<Self> {
+        paths::app_data_dir().map(|dir| Self::new(dir.join(COMPONENTS_DIR)))
+    }
+
+    pub fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    /// Components listed in the manifest, or none if nothing was saved yet.
+    pub fn components(&self) -> Result<Vec<SavedComponent>> {
+        let path = self.dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let manifest = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(serde_json::from_str(&manifest)?)
+    }
+
+    pub fn design_tokens(&self) -> Result<Option<String>> {
+        match self.dir.join(DESIGN_TOKENS_FILE).exists() {
+            true => self.read_file(DESIGN_TOKENS_FILE).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    pub fn read_file(&self, file_name: &str) -> Result<String> {
+        let path = self.resolve(file_name)?;
+        let size = std::fs::metadata(&path)?.len();
+        if size > MAX_FILE_BYTES {
+            bail!("{} is too large ({} bytes)", file_name, size);
+        }
+        Ok(std::fs::read_to_string(&path)?)
+    }
+
+    // Only bare file names are accepted, and the canonical path must still be
+    // inside the directory so symlinks can't lead out of it
+    fn resolve(&self, file_name: &str) -> Result<PathBuf> {
+        if Path::new(file_name).file_name().and_then(|n| n.to_str()) != Some(file_name) {
+            bail!("Invalid file name: {}", file_name);
+        }
+
+        let dir = self.dir.canonicalize()
+            .with_context(|| format!("No components directory at {}", self.dir.display()))?;
+        let path = dir.join(file_name).canonicalize()
+            .with_context(|| format!("No such file: {}", file_name))?;
+        if !path.starts_with(&dir) {
+            bail!("{} is outside the components directory", file_name);
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_outside_the_directory_are_rejected() {
+        let root = std::env::temp_dir().join(format!("crystalforge-components-{}", std::process::id()));
+        let dir = root.join(COMPONENTS_DIR);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Button.tsx"), "export const Button = () => null;").unwrap();
+        std::fs::write(root.join("secret.txt"), "secret").unwrap();
+
+        let library = ComponentLibrary::new(dir);
+        assert!(library.read_file("Button.tsx").unwrap().contains("Button"));
+        assert!(library.read_file("../secret.txt").is_err());
+        assert!(library.read_file("/etc/passwd").is_err());
+        assert!(library.components().unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use super::ComponentLibrary;
+use crate::llm::tools::{Tool, ToolRegistry};
+use crate::llm::types::ToolDefinition;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// Registers the read-only component library tools.
+pub fn register(registry: &mut ToolRegistry, library: ComponentLibrary) {
+    registry.register(ListComponents(library.clone()));
+    registry.register(ReadComponent(library.clone()));
+    registry.register(ListDesignTokens(library));
+}
+
+struct ListComponents(ComponentLibrary);
+
+#[async_trait]
+impl Tool for ListComponents {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_components".to_string(),
+            description: "List the components saved in the user's library, with their descriptions and tags".to_string(),
+            parameters: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _arguments: Value) -> Result<String> {
+        let components: Vec<Value> = self.0.components()?
+            .into_iter()
+            .map(|c| json!({
+                "name": c.name,
+                "fileName": c.file_name,
+                "description": c.description,
+                "tags": c.tags,
+                "crystal": c.crystal,
+            }))
+            .collect();
+        Ok(serde_json::to_string(&components)?)
+    }
+}
+
+struct ReadComponent(ComponentLibrary);
+
+#[async_trait]
+impl Tool for ReadComponent {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_component".to_string(),
+            description: "Read the source code of a saved component".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Component name or file name, as returned by list_components" }
+                },
+                "required": ["name"]
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let name = arguments["name"].as_str().ok_or_else(|| anyhow!("name is required"))?;
+        let component = self.0.components()?
+            .into_iter()
+            .find(|c| c.name == name || c.file_name == name)
+            .ok_or_else(|| anyhow!("No component named {}", name))?;
+        self.0.read_file(&component.file_name)
+    }
+}
+
+struct ListDesignTokens(ComponentLibrary);
+
+#[async_trait]
+impl Tool for ListDesignTokens {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_design_tokens".to_string(),
+            description: "Get the project's design tokens (colors, spacing, typography) as JSON".to_string(),
+            parameters: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _arguments: Value) -> Result<String> {
+        Ok(self.0.design_tokens()?.unwrap_or_else(|| "No design tokens are defined.".to_string()))
+    }
+}
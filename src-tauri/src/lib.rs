@@ -1,14 +1,232 @@
+mod benchmark;
+mod components;
 mod hardware;
 mod llm;
+mod settings;
 
+use benchmark::{BenchmarkResult, BenchmarkStore};
+use components::ComponentLibrary;
+use components::index::{ComponentIndex, ComponentMatch, IndexUpdate};
 use hardware::HardwareInfo;
-use llm::{LLMRouter, ServerStatus, ModelInfo, GenerateRequest, GenerateResponse, ChatRequest, ChatResponse, Message};
+use llm::{LLMRouter, ServerStatus, ModelInfo, GenerateRequest, GenerateResponse, ChatRequest, ChatResponse, Message, RunningModel, TokenCallback, PullProgressCallback, InFlightRequests, Cancelled, LLMError, LLMResult, ProviderConfig, ProviderInfo, SamplingOptions, EmbeddingResponse, ModelKind, ToolDefinition, UsageReport, UsageTracker};
+use llm::{continuation, images, structured, tools};
+use llm::cache::{CacheKey, CacheStats, Endpoint, ResponseCache};
+use llm::scheduler::{Job, Priority, QueueStatus, Schedule, Scheduler};
+use llm::tools::ToolRegistry;
+use llm::stream::{TokenEvent, StreamCompleteEvent, PullProgressEvent, ToolCallEvent, ToolCallback, QueueCallback, TOKEN_EVENT, COMPLETE_EVENT, PULL_PROGRESS_EVENT, TOOL_CALL_EVENT, QUEUE_EVENT};
+use arc_swap::ArcSwap;
+use settings::{Settings, SettingsSnapshot, SettingsStore};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
 use tokio::sync::Mutex;
 
-// Create a shared router instance
+// Commands read the router without locking; settings changes swap in an
+// updated copy, so requests already running finish on the old one
 struct AppState {
-    llm_router: Arc<Mutex<LLMRouter>>,
+    llm_router: ArcSwap<LLMRouter>,
+    scheduler: Arc<Scheduler>,
+    settings: Mutex<SettingsStore>,
+    benchmarks: Mutex<BenchmarkStore>,
+    in_flight: InFlightRequests,
+    usage: UsageTracker,
+    tools: ToolRegistry,
+    component_index: Mutex<ComponentIndex>,
+    response_cache: ResponseCache,
+}
+
+// Run a generation under its request id so cancel_generation can abort it.
+// Cancellation is its own error kind so the frontend doesn't have to treat
+// it as a failure.
+async fn run_cancellable<T, F>(state: &AppState, request_id: &str, future: F) -> Result<T, LLMError>
+where
+    F: std::future::Future<Output = LLMResult<T>>,
+{
+    match state.in_flight.run(request_id, future).await {
+        Ok(result) => result,
+        Err(Cancelled) => Err(LLMError::Cancelled { request_id: request_id.to_string() }),
+    }
+}
+
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Fill in the provider and model from settings when the frontend leaves them out
+async fn resolve_target(
+    state: &AppState,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<(String, String), LLMError> {
+    let settings = state.settings.lock().await.effective();
+    let provider = provider.or(settings.default_provider).unwrap_or_default();
+    let model = model.or(settings.default_model)
+        .ok_or_else(|| LLMError::validation("No model selected and no default model configured"))?;
+    Ok((provider, model))
+}
+
+// The embedding model from settings, if one is configured
+async fn embedding_target(state: &AppState) -> Option<(String, String)> {
+    let settings = state.settings.lock().await.effective();
+    settings.embedding_provider.zip(settings.embedding_model)
+}
+
+fn component_library() -> Result<ComponentLibrary, LLMError> {
+    ComponentLibrary::open().ok_or_else(|| LLMError::Internal { message: "No app data directory".to_string() })
+}
+
+fn saved_components(library: &ComponentLibrary) -> Result<Vec<components::SavedComponent>, LLMError> {
+    library.components().map_err(|e| LLMError::Internal { message: format!("{:#}", e) })
+}
+
+// Cache key for a request that opted into the response cache. Continued
+// output isn't cached, since it's stitched from several generations.
+async fn cache_key(
+    state: &AppState,
+    router: &LLMRouter,
+    provider: &str,
+    endpoint: Endpoint,
+    request: &ChatRequest,
+    cache: Option<bool>,
+    auto_continue: Option<bool>,
+) -> Option<CacheKey> {
+    if !cache.unwrap_or(false) || auto_continue.unwrap_or(false) {
+        return None;
+    }
+    state.response_cache.key(router, provider, endpoint, request).await
+}
+
+// A cached response stands in for the whole generation, so a stream gets
+// its text as a single token
+fn cache_hit(state: &AppState, key: Option<&CacheKey>, on_token: Option<&TokenCallback>) -> Option<ChatResponse> {
+    let hit = state.response_cache.get(key?)?;
+    if let Some(on_token) = on_token {
+        on_token(&hit.message.content);
+    }
+    Some(hit)
+}
+
+// Parameters not given are left to the provider's defaults. `temperature`
+// predates `sampling` and takes precedence over it.
+fn sampling_options(temperature: Option<f32>, sampling: Option<SamplingOptions>) -> Result<SamplingOptions, LLMError> {
+    let mut sampling = sampling.unwrap_or_default();
+    if temperature.is_some() {
+        sampling.temperature = temperature;
+    }
+    sampling.validate()?;
+    Ok(sampling)
+}
+
+// Edit the stored settings, then validate, persist and apply them. The edit
+// runs under the settings lock, so concurrent updates can't overwrite each
+// other, and nothing takes effect unless the settings were saved. An edit
+// returning false changes nothing.
+async fn apply_settings(
+    state: &AppState,
+    edit: impl FnOnce(&mut Settings) -> bool,
+) -> anyhow::Result<SettingsSnapshot> {
+    let mut store = state.settings.lock().await;
+    let mut new_settings = store.snapshot().settings;
+    if !edit(&mut new_settings) {
+        return Ok(store.snapshot());
+    }
+    new_settings.validate()?;
+
+    let effective = settings::with_env_overrides(&new_settings);
+    let cache_max_bytes = effective.response_cache_max_bytes();
+    let mut router = LLMRouter::clone(&state.llm_router.load());
+    router.apply_provider_configs(&effective.providers)?;
+    router.set_fallback_chain(effective.fallback_chain);
+    router.set_resilience(effective.retry, effective.circuit_breaker);
+
+    store.update(new_settings)?;
+    state.llm_router.store(Arc::new(router));
+    state.response_cache.set_max_bytes(cache_max_bytes);
+    state.scheduler.set_config(effective.scheduler);
+    Ok(store.snapshot())
+}
+
+// Queues a generation on each provider it's sent to. Jobs without a crystal
+// share one turn between them.
+fn generation_schedule(
+    app: &AppHandle,
+    state: &AppState,
+    request_id: &str,
+    crystal: Option<String>,
+    priority: Option<Priority>,
+) -> Schedule {
+    Schedule {
+        scheduler: state.scheduler.clone(),
+        request_id: request_id.to_string(),
+        crystal: crystal.unwrap_or_default(),
+        priority: priority.unwrap_or_default(),
+        on_queue: Some(queue_emitter(app.clone())),
+    }
+}
+
+// Generate an id for requests where the frontend didn't supply one
+fn next_request_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("req-{}-{}", millis, REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+// Forward streamed tokens to the frontend as events keyed by request id
+fn token_emitter(app: AppHandle, request_id: String) -> TokenCallback {
+    Arc::new(move |token: &str| {
+        let event = TokenEvent {
+            request_id: request_id.clone(),
+            token: token.to_string(),
+        };
+        if let Err(e) = app.emit(TOKEN_EVENT, event) {
+            tracing::warn!("Failed to emit token event: {}", e);
+        }
+    })
+}
+
+fn emit_complete<T: serde::Serialize + Clone>(app: &AppHandle, request_id: String, response: &T) {
+    let event = StreamCompleteEvent {
+        request_id,
+        response: response.clone(),
+    };
+    if let Err(e) = app.emit(COMPLETE_EVENT, event) {
+        tracing::warn!("Failed to emit completion event: {}", e);
+    }
+}
+
+fn progress_emitter(app: AppHandle, request_id: String) -> PullProgressCallback {
+    Arc::new(move |progress| {
+        let event = PullProgressEvent {
+            request_id: request_id.clone(),
+            progress: progress.clone(),
+        };
+        if let Err(e) = app.emit(PULL_PROGRESS_EVENT, event) {
+            tracing::warn!("Failed to emit pull progress event: {}", e);
+        }
+    })
+}
+
+fn queue_emitter(app: AppHandle) -> QueueCallback {
+    Arc::new(move |event| {
+        if let Err(e) = app.emit(QUEUE_EVENT, event.clone()) {
+            tracing::warn!("Failed to emit queue event: {}", e);
+        }
+    })
+}
+
+fn tool_emitter(app: AppHandle, request_id: String) -> ToolCallback {
+    Arc::new(move |call, result| {
+        let event = ToolCallEvent {
+            request_id: request_id.clone(),
+            call: call.clone(),
+            result: result.to_string(),
+        };
+        if let Err(e) = app.emit(TOOL_CALL_EVENT, event) {
+            tracing::warn!("Failed to emit tool call event: {}", e);
+        }
+    })
 }
 
 // Hardware detection command
@@ -20,75 +238,442 @@ async fn get_hardware_info() -> Result<HardwareInfo, String> {
 // Detect all LLM servers
 #[tauri::command]
 async fn detect_llm_servers(state: tauri::State<'_, AppState>) -> Result<ServerStatus, String> {
-    let router = state.llm_router.lock().await;
+    let router = state.llm_router.load_full();
     router.detect_servers().await
         .map_err(|e| e.to_string())
 }
 
-// List all available models from all providers
+// List all available models from all providers. Embedding models are only
+// listed when asked for with kind "embedding".
 #[tauri::command]
-async fn list_available_models(state: tauri::State<'_, AppState>) -> Result<Vec<ModelInfo>, String> {
-    let router = state.llm_router.lock().await;
-    router.list_all_models().await
-        .map_err(|e| e.to_string())
+async fn list_available_models(state: tauri::State<'_, AppState>, kind: Option<ModelKind>) -> Result<Vec<ModelInfo>, String> {
+    let mut models = state.llm_router.load_full().list_all_models(kind.unwrap_or_default()).await
+        .map_err(|e| e.to_string())?;
+    state.benchmarks.lock().await.fill_performance(&mut models);
+    Ok(models)
 }
 
-// Generate code with a specific model
+// Generate code with a specific model, optionally streaming tokens as events.
+// The request id keys both the stream events and cancel_generation. With
+// auto_continue, output cut off by the token limit is continued and stitched.
+// With a JSON schema the output is validated against it (retried once) and
+// returned as compact JSON; auto_continue doesn't apply. With cache, an
+// identical earlier request is answered from the response cache. Otherwise
+// the job waits its turn on each provider it's tried on, reporting its queue
+// position as events; background jobs give way to interactive ones.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn generate_code(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
-    provider: String,
-    model: String,
+    provider: Option<String>,
+    model: Option<String>,
     prompt: String,
     temperature: Option<f32>,
-) -> Result<GenerateResponse, String> {
-    let router = state.llm_router.lock().await;
+    sampling: Option<SamplingOptions>,
+    schema: Option<serde_json::Value>,
+    stream: Option<bool>,
+    auto_continue: Option<bool>,
+    cache: Option<bool>,
+    priority: Option<Priority>,
+    crystal: Option<String>,
+    request_id: Option<String>,
+) -> Result<GenerateResponse, LLMError> {
+    let (provider, model) = resolve_target(&state, provider, model).await?;
+    let sampling = sampling_options(temperature, sampling)?;
+    let request_id = request_id.unwrap_or_else(next_request_id);
+    let stream = stream.unwrap_or(false);
     let request = GenerateRequest {
         model,
         prompt,
-        temperature,
-        max_tokens: Some(2048),
-        top_p: Some(0.9),
-        stream: false,
+        sampling,
+        schema,
+        stream,
+        schedule: Some(generation_schedule(&app, &state, &request_id, crystal, priority)),
     };
-    
-    router.generate_with_fallback(&provider, request).await
-        .map_err(|e| e.to_string())
+
+    let response = run_cancellable(&state, &request_id, async {
+        let router = state.llm_router.load_full();
+        let on_token = stream.then(|| token_emitter(app.clone(), request_id.clone()));
+        let key = cache_key(&state, &router, &provider, Endpoint::Generate, &request.clone().into(), cache, auto_continue).await;
+        if let Some(hit) = cache_hit(&state, key.as_ref(), on_token.as_ref()) {
+            return Ok(hit.into());
+        }
+
+        let response = if request.schema.is_some() {
+            structured::generate(&router, &provider, request, on_token).await
+        } else if auto_continue.unwrap_or(false) {
+            continuation::generate(&router, &provider, request, on_token).await
+        } else if let Some(on_token) = on_token {
+            router.generate_stream_with_fallback(&provider, request, on_token).await
+        } else {
+            router.generate_with_fallback(&provider, request).await
+        };
+        if let (Some(key), Ok(response)) = (&key, &response) {
+            state.response_cache.put(key, &response.clone().into());
+        }
+        response
+    }).await?;
+
+    if !response.cache_hit {
+        state.usage.record(&response.provider_id, &response.model, &response.usage);
+    }
+    if stream {
+        emit_complete(&app, request_id, &response);
+    }
+    Ok(response)
 }
 
-// Chat with a model
+// Chat with a model, optionally streaming tokens as events. Caching and
+// scheduling work as for generate_code, except that requests with tools are
+// never answered from the cache, and can't use a schema or auto_continue.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn chat_with_model(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
-    provider: String,
-    model: String,
+    provider: Option<String>,
+    model: Option<String>,
     messages: Vec<Message>,
     temperature: Option<f32>,
-) -> Result<ChatResponse, String> {
-    let router = state.llm_router.lock().await;
+    sampling: Option<SamplingOptions>,
+    schema: Option<serde_json::Value>,
+    stream: Option<bool>,
+    auto_continue: Option<bool>,
+    tools: Option<Vec<String>>,
+    cache: Option<bool>,
+    priority: Option<Priority>,
+    crystal: Option<String>,
+    request_id: Option<String>,
+) -> Result<ChatResponse, LLMError> {
+    let (provider, model) = resolve_target(&state, provider, model).await?;
+    let sampling = sampling_options(temperature, sampling)?;
+    let request_id = request_id.unwrap_or_else(next_request_id);
+    let stream = stream.unwrap_or(false);
     let request = ChatRequest {
         model,
         messages,
-        temperature,
-        max_tokens: Some(2048),
-        top_p: Some(0.9),
-        stream: false,
+        sampling,
+        schema,
+        tools: state.tools.select(&tools.unwrap_or_default())?,
+        stream,
+        schedule: Some(generation_schedule(&app, &state, &request_id, crystal, priority)),
+    };
+    if !request.tools.is_empty() && (request.schema.is_some() || auto_continue.unwrap_or(false)) {
+        return Err(LLMError::validation("Tools can't be combined with a schema or auto_continue"));
+    }
+
+    let response = run_cancellable(&state, &request_id, async {
+        let router = state.llm_router.load_full();
+        let on_token = stream.then(|| token_emitter(app.clone(), request_id.clone()));
+        let key = cache_key(&state, &router, &provider, Endpoint::Chat, &request, cache, auto_continue).await;
+        if let Some(hit) = cache_hit(&state, key.as_ref(), on_token.as_ref()) {
+            return Ok(hit);
+        }
+
+        let response = if !request.tools.is_empty() {
+            let on_tool = tool_emitter(app.clone(), request_id.clone());
+            tools::chat_with_tools(&router, &provider, request, &state.tools, on_token, Some(on_tool)).await
+        } else if request.schema.is_some() {
+            structured::chat(&router, &provider, request, on_token).await
+        } else if auto_continue.unwrap_or(false) {
+            continuation::chat(&router, &provider, request, on_token).await
+        } else if let Some(on_token) = on_token {
+            router.chat_stream_with_fallback(&provider, request, on_token).await
+        } else {
+            router.chat_with_fallback(&provider, request).await
+        };
+        if let (Some(key), Ok(response)) = (&key, &response) {
+            state.response_cache.put(key, response);
+        }
+        response
+    }).await?;
+
+    if !response.cache_hit {
+        state.usage.record(&response.provider_id, &response.model, &response.usage);
+    }
+    if stream {
+        emit_complete(&app, request_id, &response);
+    }
+    Ok(response)
+}
+
+// Embed texts with an embedding model, in input order. The provider must be
+// given, since vectors from different models can't be compared.
+#[tauri::command]
+async fn embed_texts(
+    state: tauri::State<'_, AppState>,
+    provider: String,
+    model: String,
+    inputs: Vec<String>,
+) -> Result<EmbeddingResponse, LLMError> {
+    let response = state.llm_router.load_full().embed(&provider, &model, &inputs).await?;
+    state.usage.record(&response.provider_id, &response.model, &response.usage);
+    Ok(response)
+}
+
+// Download a model (Ollama only), emitting progress events under the request
+// id. The model is listed as Downloading until the pull finishes.
+#[tauri::command]
+async fn pull_model(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    provider: Option<String>,
+    model: String,
+    request_id: Option<String>,
+) -> Result<(), LLMError> {
+    let request_id = request_id.unwrap_or_else(next_request_id);
+    let on_progress = progress_emitter(app, request_id.clone());
+    let pull = state.llm_router.load().pull_model(provider.as_deref(), &model, on_progress)?;
+    run_cancellable(&state, &request_id, pull).await
+}
+
+// Run the benchmark suite against one model on one provider and remember
+// the results for this machine. Cancel with cancel_generation. It's queued
+// as a background job so other generations don't skew its timings.
+#[tauri::command]
+async fn benchmark_model(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    provider: String,
+    model: String,
+    request_id: Option<String>,
+) -> Result<BenchmarkResult, LLMError> {
+    let request_id = request_id.unwrap_or_else(next_request_id);
+    let (provider, client) = state.llm_router.load().resolve(&provider)?;
+    let job = Job {
+        request_id: request_id.clone(),
+        provider_id: provider.clone(),
+        crystal: String::new(),
+        priority: Priority::Background,
     };
-    
-    router.chat_with_fallback(&provider, request).await
+    let result = run_cancellable(&state, &request_id, async {
+        let _permit = state.scheduler.acquire(job, Some(queue_emitter(app))).await;
+        benchmark::runner::run_benchmark(client, &provider, &model).await
+    }).await?;
+
+    state.benchmarks.lock().await.record(result.clone())?;
+    Ok(result)
+}
+
+// Delete a model from a provider's disk
+#[tauri::command]
+async fn delete_model(state: tauri::State<'_, AppState>, provider: String, model: String) -> Result<(), LLMError> {
+    let client = state.llm_router.load().client(&provider)?;
+    client.delete_model(&model).await
+}
+
+// Load a model into memory ahead of use; negative keep-alive keeps it
+// resident until unloaded, which LM Studio doesn't support
+#[tauri::command]
+async fn load_model(
+    state: tauri::State<'_, AppState>,
+    provider: String,
+    model: String,
+    keep_alive_secs: Option<i64>,
+) -> Result<(), LLMError> {
+    let client = state.llm_router.load().client(&provider)?;
+    client.load_model(&model, keep_alive_secs).await
+}
+
+// Free the memory held by a loaded model
+#[tauri::command]
+async fn unload_model(state: tauri::State<'_, AppState>, provider: String, model: String) -> Result<(), LLMError> {
+    let client = state.llm_router.load().client(&provider)?;
+    client.unload_model(&model).await
+}
+
+// Models currently resident in memory on any provider
+#[tauri::command]
+async fn list_running_models(state: tauri::State<'_, AppState>) -> Result<Vec<RunningModel>, String> {
+    let router = state.llm_router.load_full();
+    Ok(router.running_models().await)
+}
+
+// Cumulative token usage of generate_code and chat_with_model, for the
+// session and per model
+#[tauri::command]
+fn get_token_usage(state: tauri::State<'_, AppState>) -> UsageReport {
+    state.usage.report()
+}
+
+// Size of the response cache on disk
+#[tauri::command]
+fn get_cache_stats(state: tauri::State<'_, AppState>) -> CacheStats {
+    state.response_cache.stats()
+}
+
+#[tauri::command]
+fn clear_response_cache(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.response_cache.clear().map_err(|e| format!("{:#}", e))
+}
+
+// Embed saved components that are new or changed and drop deleted ones from
+// the search index. The frontend calls this after saving or deleting.
+#[tauri::command]
+async fn update_component_index(state: tauri::State<'_, AppState>) -> Result<IndexUpdate, LLMError> {
+    let (provider, model) = embedding_target(&state).await
+        .ok_or_else(|| LLMError::validation("No embedding model configured"))?;
+    let components = saved_components(&component_library()?)?;
+    let router = state.llm_router.load_full();
+    state.component_index.lock().await.sync(&router, &provider, &model, &components).await
+}
+
+// Search saved components by meaning and keywords, best match first. Without
+// an embedding model, or if it can't be reached, only keywords count.
+#[tauri::command]
+async fn search_components(
+    state: tauri::State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ComponentMatch>, LLMError> {
+    let limit = limit.unwrap_or(20);
+    let components = saved_components(&component_library()?)?;
+    let mut index = state.component_index.lock().await;
+
+    if let Some((provider, model)) = embedding_target(&state).await {
+        let router = state.llm_router.load_full();
+        match index.query(&router, &provider, &model, components.clone(), &query, limit).await {
+            Ok(matches) => return Ok(matches),
+            Err(e) => tracing::warn!("Semantic component search failed, matching keywords only: {}", e),
+        }
+    }
+    Ok(index.search(&query, None, components, limit))
+}
+
+// Read a local image (e.g. a screenshot or mockup), downsize it and return
+// it base64-encoded for a message's `images`. Messages with images are only
+// sent to vision models.
+#[tauri::command]
+async fn encode_image(path: String) -> Result<String, LLMError> {
+    tokio::task::spawn_blocking(move || images::encode_image_file(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| LLMError::Internal { message: e.to_string() })?
+}
+
+// Tools chat_with_model can offer the model, by name
+#[tauri::command]
+fn list_tools(state: tauri::State<'_, AppState>) -> Vec<ToolDefinition> {
+    state.tools.definitions()
+}
+
+// Generations running and queued, by provider id
+#[tauri::command]
+fn get_queue_status(state: tauri::State<'_, AppState>) -> HashMap<String, QueueStatus> {
+    state.scheduler.status()
+}
+
+// Abort an in-flight generate_code, chat_with_model or pull_model call by request id
+#[tauri::command]
+fn cancel_generation(state: tauri::State<'_, AppState>, request_id: String) -> bool {
+    state.in_flight.cancel(&request_id)
+}
+
+// List registered LLM providers
+#[tauri::command]
+async fn list_llm_providers(state: tauri::State<'_, AppState>) -> Result<Vec<ProviderInfo>, String> {
+    Ok(state.llm_router.load().list_providers())
+}
+
+// Register a provider, replacing any existing provider with the same id
+#[tauri::command]
+async fn add_llm_provider(
+    state: tauri::State<'_, AppState>,
+    config: ProviderConfig,
+) -> Result<(), String> {
+    apply_settings(&state, |settings| {
+        match settings.providers.iter_mut().find(|p| p.id == config.id) {
+            Some(existing) => *existing = config,
+            None => settings.providers.push(config),
+        }
+        true
+    }).await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Remove a provider by id
+#[tauri::command]
+async fn remove_llm_provider(state: tauri::State<'_, AppState>, id: String) -> Result<bool, String> {
+    let mut removed = false;
+    apply_settings(&state, |settings| {
+        let before = settings.providers.len();
+        settings.providers.retain(|p| p.id != id);
+        removed = settings.providers.len() != before;
+        if settings.default_provider.as_deref() == Some(id.as_str()) {
+            settings.default_provider = None;
+        }
+        settings.fallback_chain.retain(|p| *p != id);
+        if settings.embedding_provider.as_deref() == Some(id.as_str()) {
+            settings.embedding_provider = None;
+            settings.embedding_model = None;
+        }
+        settings.scheduler.provider_concurrency.remove(&id);
+        removed
+    }).await
+        .map(|_| removed)
         .map_err(|e| e.to_string())
 }
 
+// Read settings as stored, plus any environment overrides in effect
+#[tauri::command]
+async fn get_settings(state: tauri::State<'_, AppState>) -> Result<SettingsSnapshot, String> {
+    Ok(state.settings.lock().await.snapshot())
+}
+
+// Replace settings; endpoint changes take effect immediately
+#[tauri::command]
+async fn update_settings(
+    state: tauri::State<'_, AppState>,
+    settings: Settings,
+) -> Result<SettingsSnapshot, String> {
+    apply_settings(&state, |stored| {
+        *stored = settings;
+        true
+    }).await
+        .map_err(|e| format!("{:#}", e))
+}
+
 // Get optimal model for hardware
 #[tauri::command]
 fn get_optimal_model(hardware: HardwareInfo) -> String {
     hardware::get_optimal_model_for_hardware(&hardware)
 }
 
+fn builtin_tools() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    match components::ComponentLibrary::open() {
+        Some(library) => components::tools::register(&mut registry, library),
+        None => tracing::warn!("No app data directory; component tools are unavailable"),
+    }
+    registry
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let settings = SettingsStore::load();
+    let effective = settings.effective();
+    let response_cache = ResponseCache::open(effective.response_cache_max_bytes());
+    let scheduler = Scheduler::new(effective.scheduler.clone());
+    let mut router = LLMRouter::new();
+    if let Err(e) = router.apply_provider_configs(&effective.providers) {
+        tracing::error!("Failed to configure LLM providers from settings: {:#}", e);
+        router.apply_provider_configs(&Settings::default().providers)
+            .expect("default provider configs are valid");
+    }
+    router.set_fallback_chain(effective.fallback_chain);
+    router.set_resilience(effective.retry, effective.circuit_breaker);
+
     let app_state = AppState {
-        llm_router: Arc::new(Mutex::new(LLMRouter::new())),
+        llm_router: ArcSwap::from_pointee(router),
+        scheduler,
+        settings: Mutex::new(settings),
+        benchmarks: Mutex::new(BenchmarkStore::load()),
+        in_flight: InFlightRequests::new(),
+        usage: UsageTracker::new(),
+        tools: builtin_tools(),
+        component_index: Mutex::new(ComponentIndex::load(ComponentLibrary::open().map(|l| l.index_path()))),
+        response_cache,
     };
 
     tauri::Builder::default()
@@ -101,6 +686,27 @@ pub fn run() {
             list_available_models,
             generate_code,
             chat_with_model,
+            embed_texts,
+            cancel_generation,
+            get_queue_status,
+            pull_model,
+            benchmark_model,
+            delete_model,
+            load_model,
+            unload_model,
+            list_running_models,
+            get_token_usage,
+            get_cache_stats,
+            clear_response_cache,
+            list_tools,
+            encode_image,
+            update_component_index,
+            search_components,
+            list_llm_providers,
+            add_llm_provider,
+            remove_llm_provider,
+            get_settings,
+            update_settings,
             get_optimal_model
         ])
         .run(tauri::generate_context!())
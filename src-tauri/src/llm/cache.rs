@@ -0,0 +1,272 @@
+use super::router::LLMRouter;
+use super::sampling::SamplingOptions;
+use super::types::{ChatRequest, ChatResponse, Message};
+use crate::settings::paths;
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const CACHE_DIR: &str = "responses";
+
+// Bump when the key or entry format changes so old entries are never read
+const CACHE_VERSION: u32 = 1;
+
+/// Which endpoint produced a response. A prompt sent to /generate and the
+/// same prompt as a chat message are rendered differently, so they're
+/// cached separately.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endpoint {
+    Generate,
+    Chat,
+}
+
+// Everything that determines a response
+#[derive(Serialize)]
+struct KeyMaterial<'a> {
+    version: u32,
+    endpoint: Endpoint,
+    provider_id: &'a str,
+    model: &'a str,
+    digest: Option<&'a str>,
+    messages: &'a [Message],
+    sampling: &'a SamplingOptions,
+    schema: Option<&'a serde_json::Value>,
+}
+
+/// Identifies a cacheable request. Responses are only stored when the
+/// provider the key was made for served them, not a fallback.
+#[derive(Debug, Clone)]
+pub struct CacheKey {
+    hash: String,
+    provider_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// Completed responses on disk, content-addressed by request, for requests
+/// that opt in. Meant for deterministic requests (temperature 0 or a fixed
+/// seed), where running the model again would produce the same output.
+///
+/// Least recently used entries are evicted once the cache outgrows its size
+/// limit; a file's modification time records its last use.
+pub struct ResponseCache {
+    dir: Option<PathBuf>,
+    max_bytes: AtomicU64,
+    // Serializes eviction with writes
+    write_lock: Mutex<()>,
+}
+
+impl ResponseCache {
+    pub fn open(max_bytes: u64) -> Self {
+        Self::new(paths::app_cache_dir().map(|dir| dir.join(CACHE_DIR)), max_bytes)
+    }
+
+    pub fn new(dir: Option<PathBuf>, max_bytes: u64) -> Self {
+        Self { dir, max_bytes: AtomicU64::new(max_bytes), write_lock: Mutex::new(()) }
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        let _guard = self.write_lock.lock().unwrap();
+        self.evict();
+    }
+
+    /// Key for a request, or None if it can't be cached. Requests offering
+    /// tools aren't cached, since their answers depend on what the tools
+    /// return at the time.
+    pub async fn key(&self, router: &LLMRouter, provider: &str, endpoint: Endpoint, request: &ChatRequest) -> Option<CacheKey> {
+        if self.dir.is_none() || !request.tools.is_empty() {
+            return None;
+        }
+
+        let provider_id = router.primary_provider(provider)?;
+        let digest = router.model_digest(&provider_id, &request.model).await;
+        let material = KeyMaterial {
+            version: CACHE_VERSION,
+            endpoint,
+            provider_id: &provider_id,
+            model: &request.model,
+            digest: digest.as_deref(),
+            messages: &request.messages,
+            sampling: &request.sampling,
+            schema: request.schema.as_ref(),
+        };
+        let json = serde_json::to_vec(&material).ok()?;
+        Some(CacheKey { hash: hex(&Sha256::digest(json)), provider_id })
+    }
+
+    /// The stored response for `key`, marked as a cache hit.
+    pub fn get(&self, key: &CacheKey) -> Option<ChatResponse> {
+        let path = self.entry_path(key)?;
+        let json = std::fs::read(&path).ok()?;
+        let mut response: ChatResponse = match serde_json::from_slice(&json) {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!("Discarding unreadable cache entry {}: {}", path.display(), e);
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        if let Err(e) = touch(&path) {
+            tracing::debug!("Failed to mark cache entry {} as used: {}", path.display(), e);
+        }
+        response.cache_hit = true;
+        Some(response)
+    }
+
+    /// Stores a response under `key`. Failures only cost a future cache
+    /// miss, so they're logged rather than returned.
+    pub fn put(&self, key: &CacheKey, response: &ChatResponse) {
+        if response.provider_id != key.provider_id {
+            tracing::debug!("Not caching a response from fallback provider {}", response.provider_id);
+            return;
+        }
+        let Some(path) = self.entry_path(key) else { return };
+
+        let _guard = self.write_lock.lock().unwrap();
+        if let Err(e) = write_entry(&path, response) {
+            tracing::warn!("Failed to write cache entry {}: {:#}", path.display(), e);
+            return;
+        }
+        self.evict();
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        match &self.dir {
+            Some(dir) if dir.exists() => Ok(std::fs::remove_dir_all(dir)?),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries();
+        CacheStats {
+            entries: entries.len(),
+            bytes: entries.iter().map(|(_, size, _)| size).sum(),
+            max_bytes: self.max_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{}.json", key.hash)))
+    }
+
+    // Removes least recently used entries until the cache fits its limit.
+    // Callers hold the write lock.
+    fn evict(&self) {
+        let max_bytes = self.max_bytes.load(Ordering::Relaxed);
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= max_bytes {
+            return;
+        }
+
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, size, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => total -= size,
+                Err(e) => tracing::warn!("Failed to evict cache entry {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    // (path, size, last used) of every entry
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            return vec![];
+        };
+        entries.filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            let is_entry = metadata.is_file() && entry.path().extension().is_some_and(|ext| ext == "json");
+            is_entry.then(|| (entry.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        }).collect()
+    }
+}
+
+fn write_entry(path: &Path, response: &ChatResponse) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let temp_path = path.with_extension("json.tmp");
+    let stored = ChatResponse { cache_hit: false, ..response.clone() };
+    std::fs::write(&temp_path, serde_json::to_vec(&stored)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(SystemTime::now())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::types::{FinishReason, GenerationTimings, TokenUsage};
+
+    fn response(provider_id: &str, content: &str) -> ChatResponse {
+        ChatResponse {
+            message: Message::assistant(content),
+            model: "qwen2.5-coder:7b".to_string(),
+            provider_id: provider_id.to_string(),
+            tokens_generated: 10,
+            generation_time_ms: 500,
+            tokens_per_second: 20.0,
+            timings: GenerationTimings::default(),
+            usage: TokenUsage::new(5, 10),
+            finish_reason: FinishReason::Stop,
+            cache_hit: false,
+        }
+    }
+
+    fn key(hash: &str) -> CacheKey {
+        CacheKey { hash: hash.to_string(), provider_id: "ollama".to_string() }
+    }
+
+    #[test]
+    fn test_hits_are_flagged_and_least_recently_used_entries_evicted() {
+        let dir = std::env::temp_dir().join(format!("crystalforge-cache-{}", std::process::id()));
+        let cache = ResponseCache::new(Some(dir.clone()), u64::MAX);
+
+        cache.put(&key("a"), &response("ollama", "<A />"));
+        cache.put(&key("fallback"), &response("lmstudio", "<B />"));
+        assert!(cache.get(&key("fallback")).is_none());
+
+        let hit = cache.get(&key("a")).unwrap();
+        assert!(hit.cache_hit);
+        assert_eq!(hit.message.content, "<A />");
+
+        // Make "a" the most recently used, then shrink the cache to one entry
+        cache.put(&key("b"), &response("ollama", "<B />"));
+        let old = SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options().write(true).open(dir.join("b.json")).unwrap().set_modified(old).unwrap();
+        let entry_size = cache.stats().bytes / 2;
+        cache.set_max_bytes(entry_size);
+
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("b")).is_none());
+
+        cache.clear().unwrap();
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
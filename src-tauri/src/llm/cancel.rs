@@ -0,0 +1,103 @@
+use futures::future::{AbortHandle, Abortable};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Returned when an in-flight request was aborted via [`InFlightRequests::cancel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+struct Entry {
+    seq: u64,
+    handle: AbortHandle,
+}
+
+/// Tracks running generations by request id so they can be aborted.
+///
+/// Aborting drops the underlying future, which drops the reqwest request and
+/// closes any response stream along with it.
+#[derive(Default)]
+pub struct InFlightRequests {
+    entries: Mutex<HashMap<String, Entry>>,
+    next_seq: AtomicU64,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn run<F: Future>(&self, request_id: &str, future: F) -> Result<F::Output, Cancelled> {
+        let (handle, registration) = AbortHandle::new_pair();
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+
+        if self.lock().insert(request_id.to_string(), Entry { seq, handle }).is_some() {
+            // The earlier request keeps running but can no longer be cancelled by id
+            tracing::warn!("Request id {} reused while still in flight", request_id);
+        }
+
+        let _guard = EntryGuard { requests: self, request_id, seq };
+        Abortable::new(future, registration).await.map_err(|_| Cancelled)
+    }
+
+    /// Aborts the request with the given id. Returns false if nothing was running.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.lock().remove(request_id) {
+            Some(entry) => {
+                entry.handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// Removes the registry entry however the request ends, including when the
+// caller's future is dropped before completion
+struct EntryGuard<'a> {
+    requests: &'a InFlightRequests,
+    request_id: &'a str,
+    seq: u64,
+}
+
+impl Drop for EntryGuard<'_> {
+    fn drop(&mut self) {
+        let mut entries = self.requests.lock();
+        if entries.get(self.request_id).map(|e| e.seq) == Some(self.seq) {
+            entries.remove(self.request_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_aborts_running_request() {
+        let requests = Arc::new(InFlightRequests::new());
+        let running = requests.clone();
+        let task = tokio::spawn(async move {
+            running.run("req-1", tokio::time::sleep(Duration::from_secs(30))).await
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(requests.cancel("req-1"));
+        assert_eq!(task.await.unwrap(), Err(Cancelled));
+        assert!(!requests.cancel("req-1"));
+    }
+
+    #[tokio::test]
+    async fn test_completed_request_is_unregistered() {
+        let requests = InFlightRequests::new();
+        assert_eq!(requests.run("req-2", async { 42 }).await, Ok(42));
+        assert!(!requests.cancel("req-2"));
+    }
+}
@@ -0,0 +1,119 @@
+use super::types::LLMProvider;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A feature a request may depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Streaming,
+    Chat,
+    Embeddings,
+    StructuredOutput,
+    ToolCalling,
+    Vision,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Capability::Streaming => write!(f, "streaming"),
+            Capability::Chat => write!(f, "chat"),
+            Capability::Embeddings => write!(f, "embeddings"),
+            Capability::StructuredOutput => write!(f, "structured output"),
+            Capability::ToolCalling => write!(f, "tool calling"),
+            Capability::Vision => write!(f, "vision"),
+        }
+    }
+}
+
+/// What a server supports, independent of which model is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    pub streaming: bool,
+    pub chat: bool,
+    pub embeddings: bool,
+    pub structured_output: bool,
+    pub tool_calling: bool,
+    pub vision: bool,
+}
+
+impl ServerCapabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Streaming => self.streaming,
+            Capability::Chat => self.chat,
+            Capability::Embeddings => self.embeddings,
+            Capability::StructuredOutput => self.structured_output,
+            Capability::ToolCalling => self.tool_calling,
+            Capability::Vision => self.vision,
+        }
+    }
+}
+
+/// Capabilities by provider and server version. An unknown version is
+/// assumed to be recent, since older servers fail loudly anyway.
+pub fn capabilities_for(provider: LLMProvider, version: Option<&str>) -> ServerCapabilities {
+    let at_least = |minimum: (u32, u32, u32)| match version.and_then(parse_version) {
+        Some(version) => version >= minimum,
+        None => true,
+    };
+
+    match provider {
+        LLMProvider::Ollama => ServerCapabilities {
+            streaming: true,
+            chat: at_least((0, 1, 14)),
+            // /api/embed (batched) arrived in 0.3.0
+            embeddings: at_least((0, 3, 0)),
+            // JSON schemas in `format`; older servers only accept "json"
+            structured_output: at_least((0, 5, 0)),
+            tool_calling: at_least((0, 3, 0)),
+            vision: at_least((0, 1, 15)),
+        },
+        // LM Studio doesn't report its version over HTTP, and hosted
+        // endpoints track the current OpenAI API
+        LLMProvider::LMStudio | LLMProvider::OpenAI => ServerCapabilities {
+            streaming: true,
+            chat: true,
+            embeddings: true,
+            structured_output: true,
+            tool_calling: true,
+            vision: true,
+        },
+    }
+}
+
+// "0.5.7", "v0.3.12-rc1" -> (0, 5, 7), (0, 3, 12)
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.trim().trim_start_matches('v');
+    let core = version.split(['-', '+', ' ']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_ollama_lacks_newer_features() {
+        let old = capabilities_for(LLMProvider::Ollama, Some("0.2.8"));
+        assert!(old.chat && old.vision);
+        assert!(!old.tool_calling && !old.structured_output && !old.embeddings);
+
+        let current = capabilities_for(LLMProvider::Ollama, Some("v0.5.7"));
+        assert!(current.supports(Capability::StructuredOutput));
+
+        assert_eq!(capabilities_for(LLMProvider::Ollama, None), current);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.3.12-rc1"), Some((0, 3, 12)));
+        assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("dev"), None);
+    }
+}
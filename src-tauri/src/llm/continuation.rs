@@ -0,0 +1,241 @@
+use super::error::LLMResult;
+use super::router::LLMRouter;
+use super::stream::TokenCallback;
+use super::types::*;
+use std::sync::{Arc, Mutex};
+
+// Follow-up requests allowed after the first one is cut off
+const MAX_CONTINUATIONS: usize = 3;
+
+// How far into a continuation to look for a reopened code fence or text
+// repeated from the end of the previous output
+const SEAM_WINDOW: usize = 256;
+
+// Shorter repeats are too likely to be coincidence, e.g. "}\n"
+const MIN_OVERLAP: usize = 16;
+
+const CONTINUE_PROMPT: &str = "Your previous reply was cut off. Continue exactly where it stopped, \
+    without repeating anything and without any commentary. If it stopped inside a code block, \
+    keep writing the code without opening a new block.";
+
+/// Like `generate_with_fallback`, but while the output is cut off by the
+/// token limit the model is asked to continue and the parts are stitched
+/// together. Streams when `on_token` is given.
+pub async fn generate(
+    router: &LLMRouter,
+    provider: &str,
+    request: GenerateRequest,
+    on_token: Option<TokenCallback>,
+) -> LLMResult<GenerateResponse> {
+    let first = match &on_token {
+        Some(on_token) => router.generate_stream_with_fallback(provider, request.clone(), on_token.clone()).await?,
+        None => router.generate_with_fallback(provider, request.clone()).await?,
+    };
+    let response = continue_while_truncated(router, request.into(), first.into(), on_token).await?;
+    Ok(response.into())
+}
+
+/// Chat counterpart of [`generate`].
+pub async fn chat(
+    router: &LLMRouter,
+    provider: &str,
+    request: ChatRequest,
+    on_token: Option<TokenCallback>,
+) -> LLMResult<ChatResponse> {
+    let first = match &on_token {
+        Some(on_token) => router.chat_stream_with_fallback(provider, request.clone(), on_token.clone()).await?,
+        None => router.chat_with_fallback(provider, request.clone()).await?,
+    };
+    continue_while_truncated(router, request, first, on_token).await
+}
+
+async fn continue_while_truncated(
+    router: &LLMRouter,
+    request: ChatRequest,
+    mut response: ChatResponse,
+    on_token: Option<TokenCallback>,
+) -> LLMResult<ChatResponse> {
+    for _ in 0..MAX_CONTINUATIONS {
+        if response.finish_reason != FinishReason::Length {
+            break;
+        }
+
+        let mut messages = request.messages.clone();
+        messages.push(Message::assistant(&response.message.content));
+        messages.push(Message::user(CONTINUE_PROMPT));
+        // Stay on the provider and model that produced the first part
+        let next_request = ChatRequest { model: response.model.clone(), messages, ..request.clone() };
+
+        let next = match &on_token {
+            Some(on_token) => {
+                let seam = Arc::new(Mutex::new(Seam::new(&response.message.content)));
+                let on_continued: TokenCallback = {
+                    let seam = seam.clone();
+                    let on_token = on_token.clone();
+                    Arc::new(move |token: &str| {
+                        if let Some(text) = seam.lock().unwrap().push(token) {
+                            on_token(&text);
+                        }
+                    })
+                };
+                let next = router.chat_stream_with_fallback(&response.provider_id, next_request, on_continued).await?;
+                if let Some(text) = seam.lock().unwrap().flush() {
+                    on_token(&text);
+                }
+                next
+            }
+            None => router.chat_with_fallback(&response.provider_id, next_request).await?,
+        };
+
+        append(&mut response, next);
+    }
+
+    Ok(response)
+}
+
+fn append(response: &mut ChatResponse, next: ChatResponse) {
+    let skip = seam_skip(&response.message.content, &next.message.content);
+    response.message.content.push_str(&next.message.content[skip..]);
+
+    response.tokens_generated += next.tokens_generated;
+    response.generation_time_ms += next.generation_time_ms;
+    response.timings = std::mem::take(&mut response.timings) + next.timings;
+    response.tokens_per_second = response.timings.tokens_per_second(response.tokens_generated);
+    response.usage = response.usage + next.usage;
+    response.finish_reason = next.finish_reason;
+}
+
+// Holds back the start of a streamed continuation until the seam can be
+// trimmed, then passes tokens straight through
+struct Seam {
+    previous: String,
+    // None once the seam has been resolved
+    pending: Option<String>,
+}
+
+impl Seam {
+    fn new(previous: &str) -> Self {
+        Self { previous: previous.to_string(), pending: Some(String::new()) }
+    }
+
+    fn push(&mut self, token: &str) -> Option<String> {
+        match &mut self.pending {
+            None => Some(token.to_string()),
+            Some(pending) => {
+                pending.push_str(token);
+                if pending.len() >= SEAM_WINDOW { self.flush() } else { None }
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Option<String> {
+        let pending = self.pending.take()?;
+        let rest = &pending[seam_skip(&self.previous, &pending)..];
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+// Bytes to drop from the start of a continuation: a code fence reopened by
+// the model, then any text repeated from the end of the previous output.
+// Only looks at the first SEAM_WINDOW bytes, so a streamed continuation is
+// trimmed the same way as the complete one.
+fn seam_skip(previous: &str, continuation: &str) -> usize {
+    let window = &continuation[..floor_char_boundary(continuation, SEAM_WINDOW)];
+
+    let mut skip = 0;
+    if in_code_fence(previous) {
+        let trimmed = window.trim_start();
+        if trimmed.starts_with("```") {
+            if let Some(newline) = trimmed.find('\n') {
+                skip = window.len() - trimmed.len() + newline + 1;
+            }
+        }
+    }
+
+    skip + overlap(previous, &window[skip..])
+}
+
+// Length of the longest prefix of `continuation` that `previous` ends with
+fn overlap(previous: &str, continuation: &str) -> usize {
+    continuation.char_indices()
+        .rev()
+        .map(|(i, c)| i + c.len_utf8())
+        .find(|&end| end >= MIN_OVERLAP && previous.ends_with(&continuation[..end]))
+        .unwrap_or(0)
+}
+
+fn in_code_fence(text: &str) -> bool {
+    text.lines().filter(|line| line.trim_start().starts_with("```")).count() % 2 == 1
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREVIOUS: &str = "Here you go:\n\n```tsx\nexport function Button() {\n  const [pressed, setPres";
+
+    #[test]
+    fn test_seam_drops_reopened_fence_and_repeated_text() {
+        let continuation = "```tsx\n  const [pressed, setPressed] = useState(false);\n";
+        let skip = seam_skip(PREVIOUS, continuation);
+        assert_eq!(&continuation[skip..], "sed] = useState(false);\n");
+
+        // Outside a code block, a fence starts new content
+        let closed = "```tsx\ncode\n```\nDone";
+        assert_eq!(seam_skip(closed, "```bash\nnpm i\n```"), 0);
+    }
+
+    #[test]
+    fn test_streamed_seam_matches_complete_continuation() {
+        let continuation = format!("```tsx\n  const [pressed, setPressed] = useState(false);\n{}", "  return null;\n".repeat(30));
+
+        let mut seam = Seam::new(PREVIOUS);
+        let mut streamed = String::new();
+        for token in continuation.as_bytes().chunks(5).map(|c| std::str::from_utf8(c).unwrap()) {
+            streamed.extend(seam.push(token));
+        }
+        streamed.extend(seam.flush());
+
+        assert_eq!(streamed, &continuation[seam_skip(PREVIOUS, &continuation)..]);
+        assert!(streamed.starts_with("sed] = useState"));
+    }
+
+    #[test]
+    fn test_appended_timings_give_decode_rate_from_eval_time() {
+        let segment = |content: &str, load_ms: f64| ChatResponse {
+            message: Message::assistant(content),
+            model: "qwen2.5-coder:7b".to_string(),
+            provider_id: "ollama".to_string(),
+            tokens_generated: 100,
+            generation_time_ms: (load_ms + 2500.0) as u64,
+            tokens_per_second: 50.0,
+            timings: GenerationTimings {
+                total_ms: Some(load_ms + 2500.0),
+                load_ms: Some(load_ms),
+                eval_ms: Some(2000.0),
+                provider_reported: true,
+                ..GenerationTimings::default()
+            },
+            usage: TokenUsage::new(20, 100),
+            finish_reason: FinishReason::Length,
+            cache_hit: false,
+        };
+
+        let mut response = segment("first", 3000.0);
+        append(&mut response, segment(" second", 0.0));
+
+        assert_eq!(response.timings.eval_ms, Some(4000.0));
+        assert_eq!(response.timings.load_ms, Some(3000.0));
+        assert_eq!(response.timings.total_ms, Some(8000.0));
+        // Model load time doesn't count against the decode rate
+        assert_eq!(response.tokens_per_second, 50.0);
+    }
+}
@@ -0,0 +1,88 @@
+use super::types::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Models currently being pulled, so listings can show them as downloading
+/// before the provider knows about them.
+#[derive(Default)]
+pub struct Downloads {
+    // Keyed by (provider id, model)
+    active: Mutex<HashMap<(String, String), (LLMProvider, PullProgress)>>,
+}
+
+impl Downloads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks a model as downloading until the returned guard is dropped.
+    pub fn start(self: &Arc<Self>, provider_id: &str, provider: LLMProvider, model: &str) -> DownloadGuard {
+        let key = (provider_id.to_string(), model.to_string());
+        let progress = PullProgress {
+            model: model.to_string(),
+            status: "starting".to_string(),
+            digest: None,
+            total: None,
+            completed: None,
+            model_status: ModelStatus::Downloading,
+        };
+        self.active.lock().unwrap().insert(key.clone(), (provider, progress));
+
+        DownloadGuard { downloads: self.clone(), key }
+    }
+
+    /// Models being downloaded, as `ModelInfo` with `Downloading` status.
+    pub fn models(&self) -> Vec<ModelInfo> {
+        self.active.lock().unwrap().iter().map(|((provider_id, model), (provider, progress))| ModelInfo {
+            id: model.clone(),
+            name: model.clone(),
+            size: progress.total,
+            provider: *provider,
+            provider_id: provider_id.clone(),
+            status: ModelStatus::Downloading,
+            performance: None,
+            context_length: None,
+            quantization: None,
+            metadata: ModelMetadata::default(),
+        }).collect()
+    }
+}
+
+pub struct DownloadGuard {
+    downloads: Arc<Downloads>,
+    key: (String, String),
+}
+
+impl DownloadGuard {
+    pub fn update(&self, progress: &PullProgress) {
+        if let Some(entry) = self.downloads.active.lock().unwrap().get_mut(&self.key) {
+            entry.1 = progress.clone();
+        }
+    }
+}
+
+// Runs on success, failure and cancellation alike
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        self.downloads.active.lock().unwrap().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_is_listed_until_guard_drops() {
+        let downloads = Arc::new(Downloads::new());
+        let guard = downloads.start("ollama", LLMProvider::Ollama, "qwen2.5-coder:7b");
+
+        let listed = downloads.models();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].provider_id, "ollama");
+        assert!(matches!(listed[0].status, ModelStatus::Downloading));
+
+        drop(guard);
+        assert!(downloads.models().is_empty());
+    }
+}
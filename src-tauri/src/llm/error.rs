@@ -0,0 +1,194 @@
+use reqwest::StatusCode;
+use serde::Serialize;
+
+pub type LLMResult<T> = Result<T, LLMError>;
+
+/// Errors from talking to a provider, serialized for the frontend as
+/// `{ "kind": "...", ... }` so it can react without parsing messages.
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LLMError {
+    #[error("Could not connect to {url}: {message}")]
+    ConnectionRefused { url: String, message: String },
+
+    #[error("Request timed out: {message}")]
+    Timeout { message: String },
+
+    #[error("Model {model} not found: {message}")]
+    ModelNotFound { model: String, message: String },
+
+    #[error("{context} ({status}): {body}")]
+    HttpStatus { context: String, status: u16, body: String },
+
+    #[error("Failed to decode response: {message}")]
+    Decode { message: String },
+
+    /// The provider reported an error in-band, e.g. in the middle of a stream
+    #[error("{message}")]
+    Provider { message: String },
+
+    /// The prompt plus the requested output doesn't fit the model's context
+    /// window; the UI can offer to trim the conversation or raise num_ctx
+    #[error("Context window exceeded: {message}")]
+    ContextOverflow { message: String },
+
+    /// Every provider that could serve the request has its circuit open
+    #[error("{message}")]
+    Unavailable { message: String },
+
+    /// The provider doesn't offer this operation
+    #[error("{message}")]
+    Unsupported { message: String },
+
+    #[error("Request {request_id} was cancelled")]
+    Cancelled { request_id: String },
+
+    #[error("{message}")]
+    Validation { message: String },
+
+    /// Structured output still didn't match the schema after a retry
+    #[error("Response did not match the schema: {message}")]
+    SchemaMismatch { message: String, output: String },
+
+    #[error("{message}")]
+    Internal { message: String },
+}
+
+impl LLMError {
+    /// Builds the error for a non-success response. `model` is the model the
+    /// request was for, so a 404 about it can be reported as a missing model.
+    /// Other 404s, e.g. from a base URL missing its `/v1`, stay HTTP errors.
+    pub fn from_status(context: &str, model: Option<&str>, status: StatusCode, body: String) -> Self {
+        if is_context_overflow(&body) {
+            return LLMError::ContextOverflow { message: body };
+        }
+        if let Some(model) = model {
+            let lower = body.to_lowercase();
+            let not_found = lower.contains("not found");
+            let about_model = body.contains(model) || not_found;
+            if (status == StatusCode::NOT_FOUND && about_model) || (lower.contains("model") && not_found) {
+                return LLMError::ModelNotFound { model: model.to_string(), message: body };
+            }
+        }
+
+        LLMError::HttpStatus { context: context.to_string(), status: status.as_u16(), body }
+    }
+
+    /// An error the provider reported in-band.
+    pub fn provider(message: impl Into<String>) -> Self {
+        let message = message.into();
+        match is_context_overflow(&message) {
+            true => LLMError::ContextOverflow { message },
+            false => LLMError::Provider { message },
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        LLMError::Validation { message: message.into() }
+    }
+
+    /// Whether the error is likely to go away on its own: connection failures,
+    /// timeouts, rate limiting, and 5xx responses (e.g. LM Studio swapping
+    /// models or Ollama still loading one).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LLMError::ConnectionRefused { .. } | LLMError::Timeout { .. } => true,
+            LLMError::HttpStatus { status, .. } => {
+                *status == StatusCode::REQUEST_TIMEOUT.as_u16()
+                    || *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+                    || (500..600).contains(status)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Ollama: "input length exceeds maximum context length", LM Studio: "...the
+// model is loaded with context length of only 4096 tokens..." on overflow,
+// OpenAI: "context_length_exceeded" / "maximum context length is ..."
+fn is_context_overflow(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("context_length_exceeded")
+        || lower.contains("maximum context length")
+        || (lower.contains("context") && ["exceed", "overflow", "too long"].iter().any(|hint| lower.contains(hint)))
+}
+
+impl From<reqwest::Error> for LLMError {
+    fn from(error: reqwest::Error) -> Self {
+        let message = error.to_string();
+        if error.is_timeout() {
+            LLMError::Timeout { message }
+        } else if error.is_connect() {
+            let url = error.url().map(|u| u.to_string()).unwrap_or_default();
+            LLMError::ConnectionRefused { url, message }
+        } else if error.is_decode() {
+            LLMError::Decode { message }
+        } else if let Some(status) = error.status() {
+            LLMError::HttpStatus { context: "Request failed".to_string(), status: status.as_u16(), body: message }
+        } else {
+            LLMError::Internal { message }
+        }
+    }
+}
+
+impl From<serde_json::Error> for LLMError {
+    fn from(error: serde_json::Error) -> Self {
+        LLMError::Decode { message: error.to_string() }
+    }
+}
+
+impl From<anyhow::Error> for LLMError {
+    fn from(error: anyhow::Error) -> Self {
+        LLMError::Internal { message: format!("{:#}", error) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_found_becomes_model_not_found() {
+        let body = r#"{"error":"model \"codellama:70b\" not found, try pulling it first"}"#.to_string();
+        let error = LLMError::from_status("Generation failed", Some("codellama:70b"), StatusCode::NOT_FOUND, body);
+        assert!(matches!(error, LLMError::ModelNotFound { ref model, .. } if model == "codellama:70b"));
+
+        let error = LLMError::from_status("Failed to list models", None, StatusCode::NOT_FOUND, String::new());
+        assert!(matches!(error, LLMError::HttpStatus { status: 404, .. }));
+    }
+
+    #[test]
+    fn test_wrong_base_url_is_not_a_missing_model() {
+        // LM Studio's answer when the base URL lacks /v1
+        let body = r#"{"error":"Unexpected endpoint or method. (POST /chat/completions)"}"#.to_string();
+        let error = LLMError::from_status("Chat failed", Some("qwen2.5-coder-7b-instruct"), StatusCode::NOT_FOUND, body);
+        assert!(matches!(error, LLMError::HttpStatus { status: 404, .. }));
+
+        let body = r#"{"error":{"message":"The model `gpt-5` does not exist","code":"model_not_found"}}"#.to_string();
+        let error = LLMError::from_status("Chat failed", Some("gpt-5"), StatusCode::NOT_FOUND, body);
+        assert!(matches!(error, LLMError::ModelNotFound { .. }));
+    }
+
+    #[test]
+    fn test_context_length_errors_become_context_overflow() {
+        let ollama = r#"{"error":"the input length exceeds the context length"}"#.to_string();
+        let error = LLMError::from_status("Chat failed", Some("llama3.1:8b"), StatusCode::BAD_REQUEST, ollama);
+        assert!(matches!(error, LLMError::ContextOverflow { .. }));
+
+        let lmstudio = "Trying to keep the first 5120 tokens when context the overflows. However, the model is loaded with context length of only 4096 tokens";
+        assert!(matches!(LLMError::provider(lmstudio), LLMError::ContextOverflow { .. }));
+        assert!(matches!(LLMError::provider("Stream failed: out of memory"), LLMError::Provider { .. }));
+        assert_eq!(serde_json::to_value(LLMError::provider(lmstudio)).unwrap()["kind"], "context_overflow");
+    }
+
+    #[test]
+    fn test_errors_serialize_with_kind_tag() {
+        let error = LLMError::from_status("Chat failed", Some("gpt-4o"), StatusCode::SERVICE_UNAVAILABLE, "busy".to_string());
+        assert!(error.is_transient());
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "http_status");
+        assert_eq!(json["status"], 503);
+        assert_eq!(json["body"], "busy");
+    }
+}
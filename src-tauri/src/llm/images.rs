@@ -0,0 +1,87 @@
+use super::error::{LLMError, LLMResult};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+// Vision encoders tile or rescale anything bigger, so larger images only
+// cost bandwidth. 1568px is the longest edge most of them use unscaled.
+const MAX_DIMENSION: u32 = 1568;
+
+// Refuse to decode anything bigger than a generous screenshot
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Reads an image from disk, shrinks it to fit within `MAX_DIMENSION` and
+/// returns it base64-encoded as PNG, ready for `Message::images`. PNG keeps
+/// the text in UI screenshots legible.
+pub fn encode_image_file(path: &Path) -> LLMResult<String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| LLMError::validation(format!("Can't read {}: {}", path.display(), e)))?
+        .len();
+    if size > MAX_FILE_BYTES {
+        return Err(LLMError::validation(format!(
+            "{} is too large ({} MB, limit {} MB)",
+            path.display(),
+            size / (1024 * 1024),
+            MAX_FILE_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let image = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| LLMError::validation(format!("Can't read {}: {}", path.display(), e)))?
+        .decode()
+        .map_err(|e| LLMError::validation(format!("{} is not a supported image: {}", path.display(), e)))?;
+
+    encode(downsize(image))
+}
+
+fn downsize(image: DynamicImage) -> DynamicImage {
+    if image.width() <= MAX_DIMENSION && image.height() <= MAX_DIMENSION {
+        return image;
+    }
+    // Keeps the aspect ratio
+    image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Lanczos3)
+}
+
+fn encode(image: DynamicImage) -> LLMResult<String> {
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| LLMError::Internal { message: format!("Failed to encode image: {}", e) })?;
+    Ok(STANDARD.encode(png))
+}
+
+/// `data:` URL for a base64 image, as OpenAI-style `image_url` parts expect.
+/// Images attached by the frontend may be JPEG, so the type is sniffed from
+/// the encoded magic bytes.
+pub fn data_url(image: &str) -> String {
+    let mime = match image.get(..4) {
+        Some("/9j/") => "image/jpeg",
+        Some("R0lG") => "image/gif",
+        Some("UklG") => "image/webp",
+        _ => "image/png",
+    };
+    format!("data:{};base64,{}", mime, image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_large_images_are_downsized_keeping_aspect_ratio() {
+        let path = std::env::temp_dir().join(format!("crystalforge-image-{}.png", std::process::id()));
+        RgbaImage::new(3136, 1000).save(&path).unwrap();
+
+        let encoded = encode_image_file(&path).unwrap();
+        let decoded = image::load_from_memory(&STANDARD.decode(&encoded).unwrap()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1568, 500));
+        assert!(data_url(&encoded).starts_with("data:image/png;base64,iVBOR"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(encode_image_file(&path), Err(LLMError::Validation { .. })));
+    }
+}
@@ -1,7 +1,13 @@
 use super::types::*;
-use anyhow::Result;
+use super::error::{LLMError, LLMResult};
+use super::sampling::SamplingOptions;
+use super::structured::ResponseFormat;
+use super::openai::{EmbeddingsRequest, EmbeddingsResponse, ToolCallDeltas, WireMessage, WireToolCallDelta};
+use super::stream::{for_each_line, parse_sse_line, SseData, TokenCallback, STREAM_TIMEOUT};
+use super::tools::FunctionTool;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 pub struct LMStudioClient {
@@ -26,11 +32,21 @@ struct LMStudioModel {
 #[derive(Debug, Serialize)]
 struct LMStudioChatRequest {
     model: String,
-    messages: Vec<Message>,
-    temperature: f32,
-    max_tokens: i32,
-    top_p: f32,
+    messages: Vec<WireMessage>,
+    #[serde(flatten)]
+    sampling: SamplingOptions,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,7 +60,7 @@ struct LMStudioChatResponse {
 #[derive(Debug, Deserialize)]
 struct Choice {
     index: i32,
-    message: Message,
+    message: WireMessage,
     finish_reason: Option<String>,
 }
 
@@ -55,14 +71,25 @@ struct Usage {
     total_tokens: u32,
 }
 
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct LMStudioCompletionRequest {
     model: String,
     prompt: String,
-    temperature: f32,
-    max_tokens: i32,
-    top_p: f32,
+    #[serde(flatten)]
+    sampling: SamplingOptions,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,56 +107,169 @@ struct CompletionChoice {
     finish_reason: Option<String>,
 }
 
+// Native REST API (/api/v0), which unlike /v1 reports load state
+#[derive(Debug, Deserialize)]
+struct LMStudioNativeModelsResponse {
+    data: Vec<LMStudioNativeModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LMStudioNativeModel {
+    id: String,
+    // "llm", "vlm" or "embeddings"
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    state: Option<String>,
+    loaded_context_length: Option<u32>,
+    max_context_length: Option<u32>,
+}
+
+// Requests for a model that isn't loaded make LM Studio load it on demand
+// ("just in time" loading); `ttl` is the idle time before it unloads again
+#[derive(Debug, Serialize)]
+struct LMStudioLoadRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+    max_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<i64>,
+}
+
+// Streaming chunks (server-sent events)
+#[derive(Debug, Deserialize)]
+struct LMStudioChatChunk {
+    model: String,
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: Delta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    tool_calls: Option<Vec<WireToolCallDelta>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LMStudioCompletionChunk {
+    model: String,
+    choices: Vec<CompletionChoice>,
+    usage: Option<Usage>,
+}
+
 impl LMStudioClient {
     pub fn new(base_url: String) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(120))
+            .connect_timeout(Duration::from_secs(5))
             .build()
             .expect("Failed to create HTTP client");
 
         Self { client, base_url }
     }
+
+    async fn native_models(&self) -> LLMResult<Vec<LMStudioNativeModel>> {
+        let url = format!("{}/api/v0/models", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(LLMError::from_status("Failed to list models", None, response.status(), String::new()));
+        }
+
+        let models_response: LMStudioNativeModelsResponse = response.json().await?;
+        Ok(models_response.data)
+    }
+}
+
+// Model types from the native API in the vocabulary Ollama uses for
+// capabilities
+fn native_capabilities(kind: Option<&str>) -> Vec<String> {
+    let capabilities: &[&str] = match kind {
+        Some("llm") => &["completion"],
+        Some("vlm") => &["completion", "vision"],
+        Some("embeddings") => &["embedding"],
+        _ => &[],
+    };
+    capabilities.iter().map(|c| c.to_string()).collect()
+}
+
+// Versions without the native API only list loaded models
+fn native_status(state: Option<&str>) -> ModelStatus {
+    match state {
+        Some("not-loaded") => ModelStatus::NotLoaded,
+        _ => ModelStatus::Loaded,
+    }
 }
 
 #[async_trait::async_trait]
 impl LLMClient for LMStudioClient {
-    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+    fn provider(&self) -> LLMProvider {
+        LLMProvider::LMStudio
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn list_models(&self) -> LLMResult<Vec<ModelInfo>> {
         let url = format!("{}/v1/models", self.base_url);
         let response = self.client.get(&url).send().await?;
         
         if !response.status().is_success() {
-            anyhow::bail!("Failed to list models: {}", response.status());
+            return Err(LLMError::from_status("Failed to list models", None, response.status(), String::new()));
         }
 
         let models_response: LMStudioModelsResponse = response.json().await?;
+        // Older versions have no native API, so capabilities and context
+        // length stay unknown
+        let native: HashMap<String, LMStudioNativeModel> = self.native_models().await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| (m.id.clone(), m))
+            .collect();
         
         let models = models_response.data.into_iter().map(|m| {
             let (name, quantization) = parse_lmstudio_model_name(&m.id);
+            let native = native.get(&m.id);
             ModelInfo {
                 id: m.id.clone(),
                 name,
                 size: None, // LM Studio doesn't provide size in OpenAI format
                 provider: LLMProvider::LMStudio,
-                status: ModelStatus::Loaded,
+                provider_id: String::new(),
+                status: native_status(native.and_then(|n| n.state.as_deref())),
                 performance: None,
-                context_length: Some(4096), // Default, depends on model
+                context_length: native.and_then(|n| n.max_context_length),
                 quantization,
+                metadata: ModelMetadata {
+                    capabilities: native_capabilities(native.and_then(|n| n.kind.as_deref())),
+                    ..ModelMetadata::default()
+                },
             }
         }).collect();
 
         Ok(models)
     }
 
-    async fn generate(&self, request: GenerateRequest) -> Result<GenerateResponse> {
+    // LM Studio only enforces a JSON schema on chat completions, so requests
+    // with one go there
+    async fn generate(&self, request: GenerateRequest) -> LLMResult<GenerateResponse> {
+        if request.schema.is_some() {
+            return self.chat(request.into()).await.map(GenerateResponse::from);
+        }
         let url = format!("{}/v1/completions", self.base_url);
         
         let lms_request = LMStudioCompletionRequest {
             model: request.model.clone(),
             prompt: request.prompt,
-            temperature: request.temperature.unwrap_or(0.7),
-            max_tokens: request.max_tokens.unwrap_or(2048),
-            top_p: request.top_p.unwrap_or(0.9),
+            sampling: request.sampling,
             stream: false,
+            stream_options: None,
         };
 
         let start_time = Instant::now();
@@ -140,8 +280,9 @@ impl LLMClient for LMStudioClient {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await?;
-            anyhow::bail!("Generation failed: {}", error_text);
+            return Err(LLMError::from_status("Generation failed", Some(&request.model), status, error_text));
         }
 
         let lms_response: LMStudioCompletionResponse = response.json().await?;
@@ -150,33 +291,40 @@ impl LLMClient for LMStudioClient {
         let text = lms_response.choices.first()
             .map(|c| c.text.clone())
             .unwrap_or_default();
+        let finish_reason = FinishReason::from_provider(
+            lms_response.choices.first().and_then(|c| c.finish_reason.as_deref()),
+        );
         
-        let tokens_generated = lms_response.usage.completion_tokens;
-        let tokens_per_second = if generation_time_ms > 0 {
-            (tokens_generated as f64 * 1000.0) / generation_time_ms as f64
-        } else {
-            0.0
-        };
+        let usage = TokenUsage::from(lms_response.usage);
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), None);
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
 
         Ok(GenerateResponse {
             text,
             model: lms_response.model,
+            provider_id: String::new(),
             tokens_generated,
             generation_time_ms,
             tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
         })
     }
 
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+    async fn chat(&self, request: ChatRequest) -> LLMResult<ChatResponse> {
         let url = format!("{}/v1/chat/completions", self.base_url);
         
         let lms_request = LMStudioChatRequest {
             model: request.model.clone(),
-            messages: request.messages,
-            temperature: request.temperature.unwrap_or(0.7),
-            max_tokens: request.max_tokens.unwrap_or(2048),
-            top_p: request.top_p.unwrap_or(0.9),
+            messages: request.messages.into_iter().map(WireMessage::from).collect(),
+            sampling: request.sampling,
+            response_format: request.schema.map(ResponseFormat::json_schema),
+            tools: request.tools.into_iter().map(FunctionTool::from).collect(),
             stream: false,
+            stream_options: None,
         };
 
         let start_time = Instant::now();
@@ -187,52 +335,275 @@ impl LLMClient for LMStudioClient {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await?;
-            anyhow::bail!("Chat failed: {}", error_text);
+            return Err(LLMError::from_status("Chat failed", Some(&request.model), status, error_text));
         }
 
         let lms_response: LMStudioChatResponse = response.json().await?;
         let generation_time_ms = start_time.elapsed().as_millis() as u64;
         
-        let message = lms_response.choices.first()
-            .map(|c| c.message.clone())
-            .unwrap_or_else(|| Message::assistant(""));
+        let (message, finish_reason) = lms_response.choices.into_iter().next()
+            .map(|c| (Message::from(c.message), FinishReason::from_provider(c.finish_reason.as_deref())))
+            .unwrap_or_else(|| (Message::assistant(""), FinishReason::Unknown));
         
-        let tokens_generated = lms_response.usage.completion_tokens;
-        let tokens_per_second = if generation_time_ms > 0 {
-            (tokens_generated as f64 * 1000.0) / generation_time_ms as f64
-        } else {
-            0.0
-        };
+        let usage = TokenUsage::from(lms_response.usage);
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), None);
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
 
         Ok(ChatResponse {
             message,
             model: lms_response.model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn generate_stream(&self, request: GenerateRequest, on_token: TokenCallback) -> LLMResult<GenerateResponse> {
+        if request.schema.is_some() {
+            return self.chat_stream(request.into(), on_token).await.map(GenerateResponse::from);
+        }
+        let url = format!("{}/v1/completions", self.base_url);
+
+        let lms_request = LMStudioCompletionRequest {
+            model: request.model.clone(),
+            prompt: request.prompt,
+            sampling: request.sampling,
+            stream: true,
+            stream_options: Some(StreamOptions { include_usage: true }),
+        };
+
+        let start_time = Instant::now();
+        let response = self.client
+            .post(&url)
+            .json(&lms_request)
+            .timeout(STREAM_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Generation failed", Some(&request.model), status, error_text));
+        }
+
+        let mut text = String::new();
+        let mut model = request.model;
+        let mut chunks_received = 0;
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut time_to_first_token = None;
+
+        for_each_line(response, |line| {
+            let data = match parse_sse_line(line) {
+                Some(SseData::Data(data)) => data,
+                Some(SseData::Done) => return Ok(false),
+                None => return Ok(true),
+            };
+            let chunk: LMStudioCompletionChunk = serde_json::from_str(data)?;
+            if let Some(choice) = chunk.choices.first() {
+                if !choice.text.is_empty() {
+                    time_to_first_token.get_or_insert_with(|| start_time.elapsed());
+                    on_token(&choice.text);
+                    text.push_str(&choice.text);
+                    chunks_received += 1;
+                }
+                finish_reason = choice.finish_reason.clone().or(finish_reason.take());
+            }
+            model = chunk.model;
+            usage = chunk.usage.or(usage.take());
+            Ok(true)
+        }).await?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+
+        // Fall back to counting chunks when the server omits usage on streams
+        let usage = usage.map(TokenUsage::from).unwrap_or(TokenUsage::new(0, chunks_received));
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), time_to_first_token);
+        let finish_reason = FinishReason::from_provider(finish_reason.as_deref());
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
+
+        Ok(GenerateResponse {
+            text,
+            model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn chat_stream(&self, request: ChatRequest, on_token: TokenCallback) -> LLMResult<ChatResponse> {
+        let url = format!("{}/v1/chat/completions", self.base_url);
+
+        let lms_request = LMStudioChatRequest {
+            model: request.model.clone(),
+            messages: request.messages.into_iter().map(WireMessage::from).collect(),
+            sampling: request.sampling,
+            response_format: request.schema.map(ResponseFormat::json_schema),
+            tools: request.tools.into_iter().map(FunctionTool::from).collect(),
+            stream: true,
+            stream_options: Some(StreamOptions { include_usage: true }),
+        };
+
+        let start_time = Instant::now();
+        let response = self.client
+            .post(&url)
+            .json(&lms_request)
+            .timeout(STREAM_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Chat failed", Some(&request.model), status, error_text));
+        }
+
+        let mut content = String::new();
+        let mut model = request.model;
+        let mut chunks_received = 0;
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut tool_calls = ToolCallDeltas::default();
+        let mut time_to_first_token = None;
+
+        for_each_line(response, |line| {
+            let data = match parse_sse_line(line) {
+                Some(SseData::Data(data)) => data,
+                Some(SseData::Done) => return Ok(false),
+                None => return Ok(true),
+            };
+            let chunk: LMStudioChatChunk = serde_json::from_str(data)?;
+            if let Some(choice) = chunk.choices.into_iter().next() {
+                if let Some(token) = choice.delta.content.as_deref().filter(|t| !t.is_empty()) {
+                    time_to_first_token.get_or_insert_with(|| start_time.elapsed());
+                    on_token(token);
+                    content.push_str(token);
+                    chunks_received += 1;
+                }
+                if let Some(deltas) = choice.delta.tool_calls {
+                    tool_calls.push(deltas);
+                }
+                finish_reason = choice.finish_reason.or(finish_reason.take());
+            }
+            model = chunk.model;
+            usage = chunk.usage.or(usage.take());
+            Ok(true)
+        }).await?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+
+        // Fall back to counting chunks when the server omits usage on streams
+        let usage = usage.map(TokenUsage::from).unwrap_or(TokenUsage::new(0, chunks_received));
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), time_to_first_token);
+        let finish_reason = FinishReason::from_provider(finish_reason.as_deref());
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
+
+        Ok(ChatResponse {
+            message: Message { tool_calls: tool_calls.finish(), ..Message::assistant(&content) },
+            model,
+            provider_id: String::new(),
             tokens_generated,
             generation_time_ms,
             tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
         })
     }
 
-    async fn health_check(&self) -> Result<bool> {
+    async fn health_check(&self) -> LLMResult<bool> {
         let url = format!("{}/v1/models", self.base_url);
         
-        match self.client.get(&url).send().await {
-            Ok(response) => Ok(response.status().is_success()),
-            Err(_) => Ok(false),
-        }
+        let response = self.client.get(&url).send().await?;
+        Ok(response.status().is_success())
     }
 
-    async fn get_model_info(&self, model_id: &str) -> Result<ModelInfo> {
+    async fn get_model_info(&self, model_id: &str) -> LLMResult<ModelInfo> {
         let models = self.list_models().await?;
         models.into_iter()
             .find(|m| m.id == model_id)
-            .ok_or_else(|| anyhow::anyhow!("Model {} not found", model_id))
+            .ok_or_else(|| LLMError::ModelNotFound {
+                model: model_id.to_string(),
+                message: "Not in the provider's model list".to_string(),
+            })
+    }
+
+    // LM Studio has no unload endpoint; models leave memory once their TTL
+    // expires or when unloaded from the app. The API can't turn the TTL off,
+    // so a negative keep-alive is refused.
+    async fn load_model(&self, model: &str, keep_alive_secs: Option<i64>) -> LLMResult<()> {
+        if keep_alive_secs.is_some_and(|secs| secs < 0) {
+            return Err(self.unsupported("keep a model loaded until it's unloaded"));
+        }
+
+        let url = format!("{}/v1/completions", self.base_url);
+        let response = self.client
+            .post(&url)
+            .json(&LMStudioLoadRequest {
+                model,
+                prompt: "",
+                max_tokens: 1,
+                // Without a TTL LM Studio applies its default idle timeout
+                ttl: keep_alive_secs.filter(|secs| *secs > 0),
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Failed to load model", Some(model), status, error_text));
+        }
+        Ok(())
+    }
+
+    async fn embed(&self, model: &str, inputs: &[String]) -> LLMResult<EmbeddingResponse> {
+        let url = format!("{}/v1/embeddings", self.base_url);
+        let response = self.client.post(&url).json(&EmbeddingsRequest { model, input: inputs }).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Embedding failed", Some(model), status, error_text));
+        }
+
+        let embeddings: EmbeddingsResponse = response.json().await?;
+        embeddings.into_response()
+    }
+
+    async fn running_models(&self) -> LLMResult<Vec<RunningModel>> {
+        Ok(self.native_models().await?.into_iter()
+            .filter(|m| m.state.as_deref() == Some("loaded"))
+            .map(|m| RunningModel {
+                model: m.id,
+                provider_id: String::new(),
+                size: None,
+                size_vram: None,
+                expires_at: None,
+                context_length: m.loaded_context_length.or(m.max_context_length),
+            })
+            .collect())
     }
 }
 
 // Helper function to parse LM Studio model names
-fn parse_lmstudio_model_name(model_id: &str) -> (String, Option<String>) {
+pub(super) fn parse_lmstudio_model_name(model_id: &str) -> (String, Option<String>) {
     // LM Studio often uses paths like "TheBloke/CodeLlama-13B-Instruct-GGUF/codellama-13b-instruct.Q4_K_M.gguf"
     let parts: Vec<&str> = model_id.split('/').collect();
     let file_name = parts.last().unwrap_or(&model_id);
@@ -255,3 +626,110 @@ fn parse_lmstudio_model_name(model_id: &str) -> (String, Option<String>) {
     
     (name, quantization)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Answers each request with the JSON body routed to its path, and hands
+    // back the request lines received
+    async fn mock_server(routes: &'static [(&'static str, &'static str)]) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let log = received.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if let Some(header_end) = text.find("\r\n\r\n") {
+                            let content_length = text.lines()
+                                .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                                .unwrap_or(0);
+                            if request.len() >= header_end + 4 + content_length {
+                                break;
+                            }
+                        }
+                        if n == 0 {
+                            break;
+                        }
+                    }
+
+                    let request_line = String::from_utf8_lossy(&request).lines().next().unwrap_or_default().to_string();
+                    let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                    log.lock().unwrap().push(request_line);
+                    let body = routes.iter().find(|(route, _)| *route == path).map_or("{}", |(_, body)| body);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        (base_url, received)
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_schema_uses_chat_completions() {
+        let (base_url, received) = mock_server(&[(
+            "/v1/chat/completions",
+            r#"{"id":"chatcmpl-1","model":"qwen2.5-coder-7b-instruct","choices":[{"index":0,"message":{"role":"assistant","content":"{\"name\":\"Button\"}"},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":5,"total_tokens":14}}"#,
+        )]).await;
+        let client = LMStudioClient::new(base_url);
+
+        let response = client.generate(GenerateRequest {
+            model: "qwen2.5-coder-7b-instruct".to_string(),
+            prompt: "Name a component".to_string(),
+            sampling: SamplingOptions::default(),
+            schema: Some(json!({"type": "object", "properties": {"name": {"type": "string"}}})),
+            stream: false,
+            schedule: None,
+        }).await.unwrap();
+
+        assert_eq!(response.text, r#"{"name":"Button"}"#);
+        assert_eq!(*received.lock().unwrap(), vec!["POST /v1/chat/completions HTTP/1.1"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_reports_native_load_state() {
+        let (base_url, _) = mock_server(&[
+            (
+                "/v1/models",
+                r#"{"object":"list","data":[{"id":"qwen2.5-coder-7b-instruct","object":"model"},{"id":"llama-3.2-3b-instruct","object":"model"}]}"#,
+            ),
+            (
+                "/api/v0/models",
+                r#"{"object":"list","data":[{"id":"qwen2.5-coder-7b-instruct","type":"llm","state":"loaded"},{"id":"llama-3.2-3b-instruct","type":"llm","state":"not-loaded"}]}"#,
+            ),
+        ]).await;
+
+        let models = LMStudioClient::new(base_url).list_models().await.unwrap();
+        assert_eq!(models[0].id, "qwen2.5-coder-7b-instruct");
+        assert!(matches!(models[0].status, ModelStatus::Loaded));
+        assert_eq!(models[1].id, "llama-3.2-3b-instruct");
+        assert!(matches!(models[1].status, ModelStatus::NotLoaded));
+    }
+
+    #[tokio::test]
+    async fn test_negative_keep_alive_is_unsupported() {
+        // Refused before any request is sent, so nothing needs to listen here
+        let client = LMStudioClient::new("http://127.0.0.1:9".to_string());
+        let result = client.load_model("qwen2.5-coder-7b-instruct", Some(-1)).await;
+        assert!(matches!(result, Err(LLMError::Unsupported { .. })));
+    }
+}
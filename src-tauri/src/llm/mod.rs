@@ -1,7 +1,31 @@
 pub mod types;
 pub mod ollama;
 pub mod lmstudio;
+pub mod openai;
 pub mod router;
+pub mod registry;
+pub mod models;
+pub mod stream;
+pub mod cancel;
+pub mod error;
+pub mod resilience;
+pub mod downloads;
+pub mod capabilities;
+pub mod usage;
+pub mod continuation;
+pub mod sampling;
+pub mod structured;
+pub mod tools;
+pub mod images;
+pub mod cache;
+pub mod scheduler;
 
 pub use types::*;
 pub use router::LLMRouter;
+pub use registry::{ProviderConfig, ProviderInfo};
+pub use stream::{PullProgressCallback, TokenCallback};
+pub use cancel::{Cancelled, InFlightRequests};
+pub use error::{LLMError, LLMResult};
+pub use resilience::{CircuitBreakerConfig, RetryPolicy};
+pub use usage::{UsageReport, UsageTracker};
+pub use sampling::SamplingOptions;
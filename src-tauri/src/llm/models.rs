@@ -0,0 +1,230 @@
+use super::lmstudio::parse_lmstudio_model_name;
+use super::ollama::parse_model_name;
+use super::types::ModelInfo;
+
+// Leading tokens that name a publisher rather than the model family,
+// e.g. "Meta-Llama-3.1-8B-Instruct"
+const PUBLISHER_PREFIXES: &[&str] = &["meta", "mistralai", "google", "microsoft", "thebloke", "bartowski"];
+
+// Name fragments of common embedding models (nomic-embed-text,
+// mxbai-embed-large, bge-m3, all-minilm), for servers that don't report
+// capabilities
+const EMBEDDING_NAME_HINTS: &[&str] = &["embed", "bge-", "minilm"];
+
+// Tag words that describe a variant of a model rather than its family
+const VARIANT_WORDS: &[&str] = &["instruct", "chat", "base", "text", "it", "hf", "latest", "gguf", "ggml"];
+
+/// Provider-independent identity of a model, used to find the same model
+/// under another provider's naming scheme.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelDescriptor {
+    pub family: String,
+    /// Parameter count as written in the name, e.g. "13b", "1.1b", "8x7b"
+    pub size: Option<String>,
+    pub variant: Vec<String>,
+    pub quantization: Option<String>,
+}
+
+/// Parses either an Ollama tag ("codellama:13b-instruct-q4_0") or an LM
+/// Studio style path ("TheBloke/CodeLlama-13B-Instruct-GGUF/codellama-13b-instruct.Q4_K_M.gguf").
+pub fn describe_model(model_id: &str) -> ModelDescriptor {
+    let (name, quantization) = if model_id.contains(':') {
+        parse_model_name(model_id)
+    } else {
+        parse_lmstudio_model_name(model_id)
+    };
+
+    let mut quantization = quantization.map(|q| q.to_lowercase());
+    let mut family = vec![];
+    let mut size = None;
+    let mut variant = vec![];
+
+    for token in tokenize(&name) {
+        if is_quantization(&token) {
+            quantization.get_or_insert(token);
+        } else if size.is_none() && is_size(&token) {
+            size = Some(token);
+        } else if size.is_none() && variant.is_empty() && !VARIANT_WORDS.contains(&token.as_str()) {
+            family.push(token);
+        } else if token != "latest" && token != "gguf" {
+            variant.push(token);
+        }
+    }
+
+    if family.len() > 1 && PUBLISHER_PREFIXES.contains(&family[0].as_str()) {
+        family.remove(0);
+    }
+
+    ModelDescriptor {
+        // "llama3.1" and "Llama-3.1" should compare equal
+        family: family.concat().replace('.', ""),
+        size,
+        variant,
+        quantization,
+    }
+}
+
+/// Picks the model among `candidates` that best matches `model_id`, if any
+/// is the same family and size.
+pub fn find_equivalent<'a>(model_id: &str, candidates: &'a [String]) -> Option<&'a str> {
+    if let Some(exact) = candidates.iter().find(|c| c.as_str() == model_id) {
+        return Some(exact);
+    }
+
+    let wanted = describe_model(model_id);
+    candidates.iter()
+        .filter_map(|candidate| {
+            let score = match_score(&wanted, &describe_model(candidate))?;
+            Some((score, candidate.as_str()))
+        })
+        // max_by_key keeps the last maximum, so reverse to prefer earlier candidates on ties
+        .rev()
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Whether a model produces embeddings rather than text. Uses the reported
+/// capabilities when there are any, otherwise the name.
+pub fn is_embedding_model(model: &ModelInfo) -> bool {
+    let capabilities = &model.metadata.capabilities;
+    if !capabilities.is_empty() {
+        return capabilities.iter().any(|c| c == "embedding") && !capabilities.iter().any(|c| c == "completion");
+    }
+    let id = model.id.to_lowercase();
+    EMBEDDING_NAME_HINTS.iter().any(|hint| id.contains(hint))
+}
+
+fn match_score(wanted: &ModelDescriptor, candidate: &ModelDescriptor) -> Option<u32> {
+    if wanted.family.is_empty() || wanted.family != candidate.family {
+        return None;
+    }
+
+    let mut score = 0;
+    match (&wanted.size, &candidate.size) {
+        (Some(a), Some(b)) if a != b => return None,
+        (Some(_), Some(_)) => score += 4,
+        _ => {}
+    }
+    if wanted.variant == candidate.variant {
+        score += 2;
+    }
+    if wanted.quantization.is_some() && wanted.quantization == candidate.quantization {
+        score += 1;
+    }
+    Some(score)
+}
+
+// Lowercase tokens split on '-', ':' and '.', keeping version and size numbers
+// like "3.1" or "6.7b" intact
+fn tokenize(name: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    for part in name.to_lowercase().split(['-', ':', ' ', '/']) {
+        let mut pieces: Vec<String> = vec![];
+        for piece in part.split('.') {
+            match pieces.last_mut() {
+                Some(last) if last.ends_with(|c: char| c.is_ascii_digit())
+                    && piece.starts_with(|c: char| c.is_ascii_digit()) => {
+                    last.push('.');
+                    last.push_str(piece);
+                }
+                _ => pieces.push(piece.to_string()),
+            }
+        }
+        tokens.extend(pieces.into_iter().filter(|p| !p.is_empty()));
+    }
+    tokens
+}
+
+fn is_size(token: &str) -> bool {
+    let digits = token.strip_suffix('b').or_else(|| token.strip_suffix('m'));
+    match digits {
+        Some(digits) if !digits.is_empty() => digits
+            .split('x')
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit() || c == '.')),
+        _ => false,
+    }
+}
+
+fn is_quantization(token: &str) -> bool {
+    let starts_quant = |prefix: &str| {
+        token.strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    };
+    starts_quant("q") || starts_quant("iq") || ["f16", "f32", "bf16", "fp16"].contains(&token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::types::{LLMProvider, ModelMetadata, ModelStatus};
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_descriptors_agree_across_naming_schemes() {
+        let ollama = describe_model("codellama:13b-instruct");
+        let lmstudio = describe_model("TheBloke/CodeLlama-13B-Instruct-GGUF/codellama-13b-instruct.Q4_K_M.gguf");
+
+        assert_eq!(ollama.family, "codellama");
+        assert_eq!(ollama.size.as_deref(), Some("13b"));
+        assert_eq!(lmstudio.family, ollama.family);
+        assert_eq!(lmstudio.size, ollama.size);
+        assert_eq!(lmstudio.variant, ollama.variant);
+        assert_eq!(lmstudio.quantization.as_deref(), Some("q4_k_m"));
+    }
+
+    #[test]
+    fn test_find_equivalent_matches_family_and_size() {
+        let lmstudio_models = ids(&[
+            "TheBloke/CodeLlama-7B-Instruct-GGUF/codellama-7b-instruct.Q4_K_M.gguf",
+            "TheBloke/CodeLlama-13B-Instruct-GGUF/codellama-13b-instruct.Q4_K_M.gguf",
+            "lmstudio-community/Meta-Llama-3.1-8B-Instruct-GGUF/Meta-Llama-3.1-8B-Instruct-Q4_K_M.gguf",
+        ]);
+
+        assert_eq!(
+            find_equivalent("codellama:13b-instruct", &lmstudio_models),
+            Some(lmstudio_models[1].as_str())
+        );
+        assert_eq!(
+            find_equivalent("llama3.1:8b-instruct-q4_K_M", &lmstudio_models),
+            Some(lmstudio_models[2].as_str())
+        );
+        assert_eq!(find_equivalent("codellama:34b-instruct", &lmstudio_models), None);
+        assert_eq!(find_equivalent("llama3.1:70b", &lmstudio_models), None);
+    }
+
+    #[test]
+    fn test_find_equivalent_prefers_matching_quantization() {
+        let ollama_models = ids(&["deepseek-coder:6.7b-instruct-q8_0", "deepseek-coder:6.7b-instruct-q4_K_M"]);
+        assert_eq!(
+            find_equivalent("deepseek-coder-6.7b-instruct.Q4_K_M.gguf", &ollama_models),
+            Some("deepseek-coder:6.7b-instruct-q4_K_M")
+        );
+    }
+
+    #[test]
+    fn test_embedding_models_by_capability_or_name() {
+        let model = |id: &str, capabilities: &[&str]| ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            size: None,
+            provider: LLMProvider::Ollama,
+            provider_id: String::new(),
+            status: ModelStatus::Loaded,
+            performance: None,
+            context_length: None,
+            quantization: None,
+            metadata: ModelMetadata {
+                capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+                ..ModelMetadata::default()
+            },
+        };
+
+        assert!(is_embedding_model(&model("nomic-embed-text:latest", &["embedding"])));
+        assert!(!is_embedding_model(&model("qwen2.5-coder:7b", &["completion", "tools"])));
+        assert!(is_embedding_model(&model("text-embedding-bge-m3", &[])));
+        assert!(!is_embedding_model(&model("llama-3.1-8b-instruct", &[])));
+    }
+}
@@ -1,15 +1,39 @@
 use super::types::*;
-use anyhow::Result;
+use super::error::{LLMError, LLMResult};
+use super::sampling::SamplingOptions;
+use super::stream::{for_each_line, PullProgressCallback, TokenCallback, STREAM_TIMEOUT};
+use super::tools::FunctionTool;
+use futures::stream::{self, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+// Downloads can run far longer than the client's generation timeout
+const PULL_TIMEOUT: Duration = Duration::from_secs(6 * 60 * 60);
+// Loading a large model from disk can also exceed it
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
 pub struct OllamaClient {
     client: Client,
     base_url: String,
+    show_cache: Arc<Mutex<ShowCache>>,
+}
+
+// /api/show results keyed by model digest, which changes whenever the model
+// itself does
+#[derive(Default)]
+struct ShowCache {
+    entries: HashMap<String, ShowMetadata>,
+    // Digests being fetched in the background
+    pending: HashSet<String>,
 }
 
+// /api/show requests in flight at once while filling the cache
+const SHOW_CONCURRENCY: usize = 4;
+
 // Ollama API response types
 #[derive(Debug, Deserialize)]
 struct OllamaTagsResponse {
@@ -22,6 +46,67 @@ struct OllamaModel {
     size: Option<u64>,
     digest: Option<String>,
     modified_at: Option<String>,
+    #[serde(default)]
+    details: OllamaModelDetails,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OllamaModelDetails {
+    family: Option<String>,
+    parameter_size: Option<String>,
+    quantization_level: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaModelRequest<'a> {
+    model: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaShowResponse {
+    template: Option<String>,
+    #[serde(default)]
+    details: OllamaModelDetails,
+    #[serde(default)]
+    model_info: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+// The parts of /api/show that ModelInfo exposes
+#[derive(Debug, Clone, Default)]
+struct ShowMetadata {
+    context_length: Option<u32>,
+    parameter_count: Option<u64>,
+    family: Option<String>,
+    quantization: Option<String>,
+    template: Option<String>,
+    capabilities: Vec<String>,
+}
+
+impl From<OllamaShowResponse> for ShowMetadata {
+    fn from(show: OllamaShowResponse) -> Self {
+        // Keys are prefixed with the architecture, e.g. "llama.context_length"
+        let architecture = show.model_info.get("general.architecture").and_then(|v| v.as_str());
+        let context_length = architecture
+            .and_then(|arch| show.model_info.get(&format!("{}.context_length", arch)))
+            .or_else(|| show.model_info.iter().find(|(k, _)| k.ends_with(".context_length")).map(|(_, v)| v))
+            .and_then(|v| v.as_u64())
+            .map(|n| n as u32);
+
+        let parameter_count = show.model_info.get("general.parameter_count")
+            .and_then(|v| v.as_u64())
+            .or_else(|| show.details.parameter_size.as_deref().and_then(parse_parameter_size));
+
+        Self {
+            context_length,
+            parameter_count,
+            family: show.details.family,
+            quantization: show.details.quantization_level,
+            template: show.template.filter(|t| !t.is_empty()),
+            capabilities: show.capabilities,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -30,9 +115,43 @@ struct OllamaGenerateResponse {
     response: String,
     done: bool,
     context: Option<Vec<i32>>,
+    #[serde(flatten)]
+    stats: OllamaStats,
+}
+
+// Sent with the final response or chunk; durations are in nanoseconds
+#[derive(Debug, Default, Deserialize)]
+struct OllamaStats {
     total_duration: Option<u64>,
+    load_duration: Option<u64>,
+    prompt_eval_count: Option<u32>,
+    prompt_eval_duration: Option<u64>,
     eval_count: Option<u32>,
     eval_duration: Option<u64>,
+    done_reason: Option<String>,
+}
+
+impl OllamaStats {
+    fn timings(&self, time_to_first_token: Option<Duration>) -> GenerationTimings {
+        let ms = |nanos: Option<u64>| nanos.map(|n| n as f64 / 1_000_000.0);
+        GenerationTimings {
+            total_ms: ms(self.total_duration),
+            load_ms: ms(self.load_duration),
+            prompt_eval_ms: ms(self.prompt_eval_duration),
+            prompt_eval_count: self.prompt_eval_count,
+            eval_ms: ms(self.eval_duration),
+            time_to_first_token_ms: time_to_first_token.map(|d| d.as_secs_f64() * 1000.0),
+            provider_reported: true,
+        }
+    }
+
+    fn finish_reason(&self) -> FinishReason {
+        FinishReason::from_provider(self.done_reason.as_deref())
+    }
+
+    fn usage(&self) -> TokenUsage {
+        TokenUsage::new(self.prompt_eval_count.unwrap_or(0), self.eval_count.unwrap_or(0))
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -41,85 +160,363 @@ struct OllamaGenerateRequest {
     prompt: String,
     stream: bool,
     options: OllamaOptions,
+    // A JSON schema the output is constrained to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
 }
 
+// Ollama's names for the sampling parameters; unset ones fall back to the
+// model's Modelfile defaults
 #[derive(Debug, Serialize)]
 struct OllamaOptions {
-    temperature: f32,
-    top_p: f32,
-    num_predict: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+}
+
+impl From<SamplingOptions> for OllamaOptions {
+    fn from(sampling: SamplingOptions) -> Self {
+        Self {
+            temperature: sampling.temperature,
+            num_predict: sampling.max_tokens,
+            top_p: sampling.top_p,
+            top_k: sampling.top_k,
+            min_p: sampling.min_p,
+            stop: sampling.stop,
+            seed: sampling.seed,
+            repeat_penalty: sampling.repeat_penalty,
+            frequency_penalty: sampling.frequency_penalty,
+            presence_penalty: sampling.presence_penalty,
+            num_ctx: sampling.num_ctx,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
 struct OllamaChatRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<OllamaMessage>,
     stream: bool,
     options: OllamaOptions,
+    // A JSON schema the output is constrained to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OllamaChatResponse {
     model: String,
-    message: Message,
+    message: OllamaMessage,
     done: bool,
-    total_duration: Option<u64>,
-    eval_count: Option<u32>,
-    eval_duration: Option<u64>,
+    #[serde(flatten)]
+    stats: OllamaStats,
+}
+
+// Ollama has no tool call ids; results are matched to calls by order and
+// `tool_name`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    // Base64 without a data: prefix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    // An object, unlike OpenAI's JSON-encoded string
+    arguments: serde_json::Value,
+}
+
+fn to_ollama_messages(messages: Vec<Message>) -> Vec<OllamaMessage> {
+    let mut call_names = HashMap::new();
+    messages.into_iter().map(|message| {
+        for call in &message.tool_calls {
+            call_names.insert(call.id.clone(), call.name.clone());
+        }
+        let tool_name = message.tool_call_id.as_ref().and_then(|id| call_names.get(id).cloned());
+
+        OllamaMessage {
+            role: message.role,
+            content: message.content,
+            images: message.images,
+            tool_calls: message.tool_calls.into_iter().map(|call| OllamaToolCall {
+                function: OllamaFunctionCall { name: call.name, arguments: call.arguments },
+            }).collect(),
+            tool_name,
+        }
+    }).collect()
+}
+
+// Calls get ids so tool results can refer back to them
+fn tool_calls_from_ollama(calls: Vec<OllamaToolCall>) -> Vec<ToolCall> {
+    calls.into_iter().enumerate().map(|(i, call)| ToolCall {
+        id: format!("call_{}", i),
+        name: call.function.name,
+        arguments: call.function.arguments,
+    }).collect()
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    model: String,
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaPullRequest<'a> {
+    model: &'a str,
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaPullProgress {
+    status: String,
+    digest: Option<String>,
+    total: Option<u64>,
+    completed: Option<u64>,
+}
+
+// A generate request without a prompt only loads or unloads the model
+#[derive(Debug, Serialize)]
+struct OllamaKeepAliveRequest<'a> {
+    model: &'a str,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaVersionResponse {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaPsResponse {
+    models: Vec<OllamaRunningModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaRunningModel {
+    name: String,
+    size: Option<u64>,
+    size_vram: Option<u64>,
+    expires_at: Option<String>,
+    context_length: Option<u32>,
+}
+
+// Streamed responses can report failures mid-body as `{"error": "..."}`
+#[derive(Debug, Deserialize)]
+struct OllamaStreamError {
+    error: String,
 }
 
 impl OllamaClient {
     pub fn new(base_url: String) -> Self {
         let client = Client::builder()
             .timeout(Duration::from_secs(120))
+            .connect_timeout(Duration::from_secs(5))
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { client, base_url }
+        Self {
+            client,
+            base_url,
+            show_cache: Arc::new(Mutex::new(ShowCache::default())),
+        }
     }
-}
 
-#[async_trait::async_trait]
-impl LLMClient for OllamaClient {
-    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+    async fn set_keep_alive(&self, model: &str, keep_alive: Option<i64>) -> LLMResult<()> {
+        let url = format!("{}/api/generate", self.base_url);
+        let response = self.client
+            .post(&url)
+            .json(&OllamaKeepAliveRequest { model, stream: false, keep_alive })
+            .timeout(LOAD_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Failed to change keep-alive", Some(model), status, error_text));
+        }
+        Ok(())
+    }
+
+    async fn tags(&self) -> LLMResult<OllamaTagsResponse> {
         let url = format!("{}/api/tags", self.base_url);
         let response = self.client.get(&url).send().await?;
-        
+
         if !response.status().is_success() {
-            anyhow::bail!("Failed to list models: {}", response.status());
+            return Err(LLMError::from_status("Failed to list models", None, response.status(), String::new()));
         }
+        Ok(response.json().await?)
+    }
 
-        let tags_response: OllamaTagsResponse = response.json().await?;
-        
-        let models = tags_response.models.into_iter().map(|m| {
-            let (name, quantization) = parse_model_name(&m.name);
+    // Fetches /api/show for models not cached yet in the background, so a
+    // large library can't hold up listing; they get their metadata on a
+    // later call
+    fn fill_show_cache(&self, models: &[OllamaModel]) {
+        let missing: Vec<(String, String)> = {
+            let mut cache = self.show_cache.lock().unwrap();
+            let cache = &mut *cache;
+            models.iter()
+                .filter_map(|m| Some((m.name.clone(), m.digest.clone()?)))
+                .filter(|(_, digest)| !cache.entries.contains_key(digest) && cache.pending.insert(digest.clone()))
+                .collect()
+        };
+        if missing.is_empty() {
+            return;
+        }
+
+        let (client, base_url, cache) = (self.client.clone(), self.base_url.clone(), self.show_cache.clone());
+        tokio::spawn(async move {
+            stream::iter(missing).for_each_concurrent(SHOW_CONCURRENCY, |(name, digest)| {
+                let (client, base_url, cache) = (&client, &base_url, &cache);
+                async move {
+                    let result = show(client, base_url, &name).await;
+                    let mut cache = cache.lock().unwrap();
+                    cache.pending.remove(&digest);
+                    match result {
+                        Ok(metadata) => {
+                            cache.entries.insert(digest, metadata);
+                        }
+                        Err(e) => tracing::debug!("No metadata for {}: {}", name, e),
+                    }
+                }
+            }).await;
+        });
+    }
+}
+
+async fn show(client: &Client, base_url: &str, model: &str) -> LLMResult<ShowMetadata> {
+    let url = format!("{}/api/show", base_url);
+    let response = client
+        .post(&url)
+        .json(&OllamaModelRequest { model })
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await?;
+        return Err(LLMError::from_status("Failed to show model", Some(model), status, error_text));
+    }
+
+    let show_response: OllamaShowResponse = response.json().await?;
+    Ok(show_response.into())
+}
+
+fn parse_stream_line<T: serde::de::DeserializeOwned>(line: &str) -> LLMResult<T> {
+    if let Ok(stream_error) = serde_json::from_str::<OllamaStreamError>(line) {
+        return Err(LLMError::provider(format!("Stream failed: {}", stream_error.error)));
+    }
+    Ok(serde_json::from_str(line)?)
+}
+
+#[async_trait::async_trait]
+impl LLMClient for OllamaClient {
+    fn provider(&self) -> LLMProvider {
+        LLMProvider::Ollama
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn list_models(&self) -> LLMResult<Vec<ModelInfo>> {
+        let tags_response = self.tags().await?;
+        self.fill_show_cache(&tags_response.models);
+        let metadata: Vec<Option<ShowMetadata>> = {
+            let mut cache = self.show_cache.lock().unwrap();
+            // Forget models that have been removed or re-pulled
+            let digests: HashSet<&str> = tags_response.models.iter().filter_map(|m| m.digest.as_deref()).collect();
+            cache.entries.retain(|digest, _| digests.contains(digest.as_str()));
+            tags_response.models.iter()
+                .map(|m| m.digest.as_ref().and_then(|d| cache.entries.get(d).cloned()))
+                .collect()
+        };
+
+        let models = tags_response.models.into_iter().zip(metadata).map(|(m, show)| {
+            let (name, parsed_quantization) = parse_model_name(&m.name);
+            let show = show.unwrap_or_default();
             ModelInfo {
                 id: m.name.clone(),
                 name,
                 size: m.size,
                 provider: LLMProvider::Ollama,
+                provider_id: String::new(),
                 status: ModelStatus::Loaded, // Ollama only shows loaded models
                 performance: None,
-                context_length: Some(4096), // Default, could be parsed from model
-                quantization,
+                context_length: show.context_length,
+                quantization: show.quantization
+                    .or(m.details.quantization_level)
+                    .or(parsed_quantization),
+                metadata: ModelMetadata {
+                    digest: m.digest,
+                    modified_at: m.modified_at,
+                    family: show.family.or(m.details.family),
+                    parameter_count: show.parameter_count
+                        .or_else(|| m.details.parameter_size.as_deref().and_then(parse_parameter_size)),
+                    template: show.template,
+                    capabilities: show.capabilities,
+                },
             }
         }).collect();
 
         Ok(models)
     }
 
-    async fn generate(&self, request: GenerateRequest) -> Result<GenerateResponse> {
+    async fn generate(&self, request: GenerateRequest) -> LLMResult<GenerateResponse> {
         let url = format!("{}/api/generate", self.base_url);
         
         let ollama_request = OllamaGenerateRequest {
             model: request.model.clone(),
             prompt: request.prompt,
             stream: false,
-            options: OllamaOptions {
-                temperature: request.temperature.unwrap_or(0.7),
-                top_p: request.top_p.unwrap_or(0.9),
-                num_predict: request.max_tokens.unwrap_or(2048),
-            },
+            options: request.sampling.into(),
+            format: request.schema,
         };
 
         let start_time = Instant::now();
@@ -130,41 +527,44 @@ impl LLMClient for OllamaClient {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await?;
-            anyhow::bail!("Generation failed: {}", error_text);
+            return Err(LLMError::from_status("Generation failed", Some(&request.model), status, error_text));
         }
 
         let ollama_response: OllamaGenerateResponse = response.json().await?;
         let generation_time_ms = start_time.elapsed().as_millis() as u64;
         
-        let tokens_generated = ollama_response.eval_count.unwrap_or(0);
-        let tokens_per_second = if generation_time_ms > 0 {
-            (tokens_generated as f64 * 1000.0) / generation_time_ms as f64
-        } else {
-            0.0
-        };
+        let usage = ollama_response.stats.usage();
+        let tokens_generated = usage.completion_tokens;
+        let timings = ollama_response.stats.timings(None);
+        let finish_reason = ollama_response.stats.finish_reason();
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
 
         Ok(GenerateResponse {
             text: ollama_response.response,
             model: ollama_response.model,
+            provider_id: String::new(),
             tokens_generated,
             generation_time_ms,
             tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
         })
     }
 
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+    async fn chat(&self, request: ChatRequest) -> LLMResult<ChatResponse> {
         let url = format!("{}/api/chat", self.base_url);
         
         let ollama_request = OllamaChatRequest {
             model: request.model.clone(),
-            messages: request.messages,
+            messages: to_ollama_messages(request.messages),
             stream: false,
-            options: OllamaOptions {
-                temperature: request.temperature.unwrap_or(0.7),
-                top_p: request.top_p.unwrap_or(0.9),
-                num_predict: request.max_tokens.unwrap_or(2048),
-            },
+            options: request.sampling.into(),
+            format: request.schema,
+            tools: request.tools.into_iter().map(FunctionTool::from).collect(),
         };
 
         let start_time = Instant::now();
@@ -175,48 +575,338 @@ impl LLMClient for OllamaClient {
             .await?;
 
         if !response.status().is_success() {
+            let status = response.status();
             let error_text = response.text().await?;
-            anyhow::bail!("Chat failed: {}", error_text);
+            return Err(LLMError::from_status("Chat failed", Some(&request.model), status, error_text));
         }
 
         let ollama_response: OllamaChatResponse = response.json().await?;
         let generation_time_ms = start_time.elapsed().as_millis() as u64;
         
-        let tokens_generated = ollama_response.eval_count.unwrap_or(0);
-        let tokens_per_second = if generation_time_ms > 0 {
-            (tokens_generated as f64 * 1000.0) / generation_time_ms as f64
-        } else {
-            0.0
-        };
+        let usage = ollama_response.stats.usage();
+        let tokens_generated = usage.completion_tokens;
+        let timings = ollama_response.stats.timings(None);
+        let tool_calls = tool_calls_from_ollama(ollama_response.message.tool_calls);
+        // Ollama reports "stop" when the reply is a tool call
+        let finish_reason = if tool_calls.is_empty() { ollama_response.stats.finish_reason() } else { FinishReason::ToolCalls };
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
 
         Ok(ChatResponse {
-            message: ollama_response.message,
+            message: Message { tool_calls, ..Message::assistant(&ollama_response.message.content) },
             model: ollama_response.model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn generate_stream(&self, request: GenerateRequest, on_token: TokenCallback) -> LLMResult<GenerateResponse> {
+        let url = format!("{}/api/generate", self.base_url);
+
+        let ollama_request = OllamaGenerateRequest {
+            model: request.model.clone(),
+            prompt: request.prompt,
+            stream: true,
+            options: request.sampling.into(),
+            format: request.schema,
+        };
+
+        let start_time = Instant::now();
+        let response = self.client
+            .post(&url)
+            .json(&ollama_request)
+            .timeout(STREAM_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Generation failed", Some(&request.model), status, error_text));
+        }
+
+        let mut text = String::new();
+        let mut model = request.model;
+        let mut stats = OllamaStats::default();
+        let mut time_to_first_token = None;
+
+        for_each_line(response, |line| {
+            let chunk: OllamaGenerateResponse = parse_stream_line(line)?;
+            if !chunk.response.is_empty() {
+                time_to_first_token.get_or_insert_with(|| start_time.elapsed());
+                on_token(&chunk.response);
+                text.push_str(&chunk.response);
+            }
+            model = chunk.model;
+            if chunk.done {
+                stats = chunk.stats;
+            }
+            Ok(!chunk.done)
+        }).await?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+        let usage = stats.usage();
+        let tokens_generated = usage.completion_tokens;
+        let timings = stats.timings(time_to_first_token);
+        let finish_reason = stats.finish_reason();
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
+
+        Ok(GenerateResponse {
+            text,
+            model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn chat_stream(&self, request: ChatRequest, on_token: TokenCallback) -> LLMResult<ChatResponse> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let ollama_request = OllamaChatRequest {
+            model: request.model.clone(),
+            messages: to_ollama_messages(request.messages),
+            stream: true,
+            options: request.sampling.into(),
+            format: request.schema,
+            tools: request.tools.into_iter().map(FunctionTool::from).collect(),
+        };
+
+        let start_time = Instant::now();
+        let response = self.client
+            .post(&url)
+            .json(&ollama_request)
+            .timeout(STREAM_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Chat failed", Some(&request.model), status, error_text));
+        }
+
+        let mut content = String::new();
+        let mut tool_calls = vec![];
+        let mut model = request.model;
+        let mut stats = OllamaStats::default();
+        let mut time_to_first_token = None;
+
+        for_each_line(response, |line| {
+            let chunk: OllamaChatResponse = parse_stream_line(line)?;
+            if !chunk.message.content.is_empty() {
+                time_to_first_token.get_or_insert_with(|| start_time.elapsed());
+                on_token(&chunk.message.content);
+                content.push_str(&chunk.message.content);
+            }
+            // Tool calls arrive whole rather than as deltas
+            tool_calls.extend(chunk.message.tool_calls);
+            model = chunk.model;
+            if chunk.done {
+                stats = chunk.stats;
+            }
+            Ok(!chunk.done)
+        }).await?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+        let usage = stats.usage();
+        let tokens_generated = usage.completion_tokens;
+        let timings = stats.timings(time_to_first_token);
+        let tool_calls = tool_calls_from_ollama(tool_calls);
+        let finish_reason = if tool_calls.is_empty() { stats.finish_reason() } else { FinishReason::ToolCalls };
+        let tokens_per_second = timings.tokens_per_second(tokens_generated);
+
+        Ok(ChatResponse {
+            message: Message { tool_calls, ..Message::assistant(&content) },
+            model,
+            provider_id: String::new(),
             tokens_generated,
             generation_time_ms,
             tokens_per_second,
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
         })
     }
 
-    async fn health_check(&self) -> Result<bool> {
+    async fn health_check(&self) -> LLMResult<bool> {
         let url = format!("{}/api/tags", self.base_url);
         
-        match self.client.get(&url).send().await {
-            Ok(response) => Ok(response.status().is_success()),
-            Err(_) => Ok(false),
+        let response = self.client.get(&url).send().await?;
+        Ok(response.status().is_success())
+    }
+
+    // Asks /api/show about this one model; size and digest come from its
+    // entry in /api/tags
+    async fn get_model_info(&self, model_id: &str) -> LLMResult<ModelInfo> {
+        let (show, tags) = futures::try_join!(show(&self.client, &self.base_url, model_id), self.tags())?;
+        // A name without a tag refers to :latest
+        let listed = tags.models.into_iter()
+            .find(|m| m.name == model_id || m.name.strip_suffix(":latest") == Some(model_id));
+        let (size, digest, modified_at) = listed.map(|m| (m.size, m.digest, m.modified_at)).unwrap_or_default();
+        let (name, parsed_quantization) = parse_model_name(model_id);
+        Ok(ModelInfo {
+            id: model_id.to_string(),
+            name,
+            size,
+            provider: LLMProvider::Ollama,
+            provider_id: String::new(),
+            status: ModelStatus::Loaded,
+            performance: None,
+            context_length: show.context_length,
+            quantization: show.quantization.or(parsed_quantization),
+            metadata: ModelMetadata {
+                digest,
+                modified_at,
+                family: show.family,
+                parameter_count: show.parameter_count,
+                template: show.template,
+                capabilities: show.capabilities,
+            },
+        })
+    }
+
+    async fn server_version(&self) -> LLMResult<Option<String>> {
+        let url = format!("{}/api/version", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(LLMError::from_status("Failed to get version", None, response.status(), String::new()));
         }
+
+        let version_response: OllamaVersionResponse = response.json().await?;
+        Ok(Some(version_response.version))
+    }
+
+    async fn delete_model(&self, model: &str) -> LLMResult<()> {
+        let url = format!("{}/api/delete", self.base_url);
+        let response = self.client
+            .delete(&url)
+            .json(&OllamaModelRequest { model })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Delete failed", Some(model), status, error_text));
+        }
+        Ok(())
+    }
+
+    async fn load_model(&self, model: &str, keep_alive_secs: Option<i64>) -> LLMResult<()> {
+        self.set_keep_alive(model, keep_alive_secs).await
+    }
+
+    async fn unload_model(&self, model: &str) -> LLMResult<()> {
+        self.set_keep_alive(model, Some(0)).await
     }
 
-    async fn get_model_info(&self, model_id: &str) -> Result<ModelInfo> {
-        let models = self.list_models().await?;
-        models.into_iter()
-            .find(|m| m.id == model_id)
-            .ok_or_else(|| anyhow::anyhow!("Model {} not found", model_id))
+    async fn embed(&self, model: &str, inputs: &[String]) -> LLMResult<EmbeddingResponse> {
+        let url = format!("{}/api/embed", self.base_url);
+        let response = self.client
+            .post(&url)
+            .json(&OllamaEmbedRequest { model, input: inputs })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Embedding failed", Some(model), status, error_text));
+        }
+
+        let embed_response: OllamaEmbedResponse = response.json().await?;
+        EmbeddingResponse::new(
+            embed_response.model,
+            embed_response.embeddings,
+            TokenUsage::new(embed_response.prompt_eval_count, 0),
+        )
+    }
+
+    async fn running_models(&self) -> LLMResult<Vec<RunningModel>> {
+        let url = format!("{}/api/ps", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(LLMError::from_status("Failed to list running models", None, response.status(), String::new()));
+        }
+
+        let ps_response: OllamaPsResponse = response.json().await?;
+        Ok(ps_response.models.into_iter().map(|m| RunningModel {
+            model: m.name,
+            provider_id: String::new(),
+            size: m.size,
+            size_vram: m.size_vram,
+            expires_at: m.expires_at,
+            context_length: m.context_length,
+        }).collect())
     }
+
+    async fn pull_model(&self, model: &str, on_progress: PullProgressCallback) -> LLMResult<()> {
+        let url = format!("{}/api/pull", self.base_url);
+        let response = self.client
+            .post(&url)
+            .json(&OllamaPullRequest { model, stream: true })
+            .timeout(PULL_TIMEOUT)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Pull failed", None, status, error_text));
+        }
+
+        let mut succeeded = false;
+        for_each_line(response, |line| {
+            let update: OllamaPullProgress = parse_stream_line(line)?;
+            succeeded = update.status == "success";
+            on_progress(&PullProgress {
+                model: model.to_string(),
+                status: update.status,
+                digest: update.digest,
+                total: update.total,
+                completed: update.completed,
+                model_status: if succeeded { ModelStatus::Loaded } else { ModelStatus::Downloading },
+            });
+            Ok(!succeeded)
+        }).await?;
+
+        if !succeeded {
+            return Err(LLMError::Provider {
+                message: format!("Pull of {} ended before it completed", model),
+            });
+        }
+        Ok(())
+    }
+}
+
+// "8.0B" -> 8_000_000_000, "137M" -> 137_000_000
+fn parse_parameter_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, multiplier) = match size.chars().last()?.to_ascii_uppercase() {
+        'B' => (&size[..size.len() - 1], 1e9),
+        'M' => (&size[..size.len() - 1], 1e6),
+        'K' => (&size[..size.len() - 1], 1e3),
+        _ => (size, 1.0),
+    };
+    number.parse::<f64>().ok().map(|n| (n * multiplier).round() as u64)
 }
 
 // Helper function to parse model names
-fn parse_model_name(full_name: &str) -> (String, Option<String>) {
+pub(super) fn parse_model_name(full_name: &str) -> (String, Option<String>) {
     // Example: "codellama:13b-instruct-q4_0" -> ("codellama-13b-instruct", "q4_0")
     let parts: Vec<&str> = full_name.split(':').collect();
     if parts.len() > 1 {
@@ -230,3 +920,81 @@ fn parse_model_name(full_name: &str) -> (String, Option<String>) {
         (full_name.to_string(), None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_show_metadata_reads_architecture_keys() {
+        let show: OllamaShowResponse = serde_json::from_value(json!({
+            "template": "{{ .Prompt }}",
+            "details": { "family": "llama", "parameter_size": "8.0B", "quantization_level": "Q4_K_M" },
+            "model_info": {
+                "general.architecture": "llama",
+                "general.parameter_count": 8030261248u64,
+                "llama.context_length": 131072
+            },
+            "capabilities": ["completion", "tools"]
+        })).unwrap();
+
+        let metadata = ShowMetadata::from(show);
+        assert_eq!(metadata.context_length, Some(131072));
+        assert_eq!(metadata.parameter_count, Some(8030261248));
+        assert_eq!(metadata.quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(metadata.capabilities, vec!["completion", "tools"]);
+    }
+
+    #[test]
+    fn test_keep_alive_request_omits_default() {
+        let unload = OllamaKeepAliveRequest { model: "llama3.1:8b", stream: false, keep_alive: Some(0) };
+        assert_eq!(serde_json::to_value(&unload).unwrap(), json!({ "model": "llama3.1:8b", "stream": false, "keep_alive": 0 }));
+
+        let load = OllamaKeepAliveRequest { model: "llama3.1:8b", stream: false, keep_alive: None };
+        assert!(serde_json::to_value(&load).unwrap().get("keep_alive").is_none());
+    }
+
+    #[test]
+    fn test_stats_give_decode_rate_without_load_or_prompt_time() {
+        let chunk: OllamaChatResponse = serde_json::from_value(json!({
+            "model": "llama3.1:8b",
+            "message": { "role": "assistant", "content": "" },
+            "done": true,
+            "total_duration": 5_000_000_000u64,
+            "load_duration": 2_000_000_000u64,
+            "prompt_eval_count": 400,
+            "prompt_eval_duration": 500_000_000u64,
+            "eval_count": 100,
+            "eval_duration": 2_000_000_000u64
+        })).unwrap();
+
+        let timings = chunk.stats.timings(None);
+        assert_eq!(timings.load_ms, Some(2000.0));
+        assert_eq!(timings.tokens_per_second(100), 50.0);
+        assert_eq!(timings.prompt_tokens_per_second(), Some(800.0));
+    }
+
+    #[test]
+    fn test_parse_parameter_size() {
+        assert_eq!(parse_parameter_size("8.0B"), Some(8_000_000_000));
+        assert_eq!(parse_parameter_size("137M"), Some(137_000_000));
+        assert_eq!(parse_parameter_size("unknown"), None);
+    }
+
+    #[test]
+    fn test_tool_results_are_sent_with_the_tool_name() {
+        let call = ToolCall { id: "call_0".to_string(), name: "list_components".to_string(), arguments: json!({}) };
+        let messages = to_ollama_messages(vec![
+            Message::user("what components do I have?"),
+            Message { tool_calls: vec![call.clone()], ..Message::assistant("") },
+            Message::tool_result(&call, "[]"),
+        ]);
+
+        assert_eq!(serde_json::to_value(&messages[1]).unwrap(), json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{ "function": { "name": "list_components", "arguments": {} } }]
+        }));
+        assert_eq!(messages[2].tool_name.as_deref(), Some("list_components"));
+    }
+}
@@ -0,0 +1,730 @@
+use super::types::*;
+use super::error::{LLMError, LLMResult};
+use super::images;
+use super::sampling::SamplingOptions;
+use super::structured::ResponseFormat;
+use super::stream::{for_each_line, parse_sse_line, SseData, TokenCallback, STREAM_TIMEOUT};
+use super::tools::FunctionTool;
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Connection settings for any server speaking the OpenAI REST API
+/// (vLLM, LocalAI, llama-swap, hosted endpoints, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAICompatibleConfig {
+    /// Base URL including the API prefix, e.g. `http://localhost:8000/v1`
+    pub base_url: String,
+    pub api_key: Option<String>,
+    /// Header carrying the API key. Defaults to `Authorization: Bearer <key>`;
+    /// any other header (e.g. `api-key`, `x-api-key`) gets the raw key.
+    pub api_key_header: Option<String>,
+    pub organization: Option<String>,
+}
+
+pub struct OpenAICompatibleClient {
+    client: Client,
+    base_url: String,
+}
+
+// OpenAI API types
+#[derive(Debug, Deserialize)]
+struct OpenAIModelsResponse {
+    data: Vec<OpenAIModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIModel {
+    id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAIChatRequest {
+    model: String,
+    messages: Vec<WireMessage>,
+    #[serde(flatten)]
+    sampling: SamplingOptions,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIChatResponse {
+    model: String,
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: WireMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        TokenUsage::new(usage.prompt_tokens, usage.completion_tokens)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIChatChunk {
+    model: String,
+    choices: Vec<ChunkChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    delta: Delta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    content: Option<String>,
+    tool_calls: Option<Vec<WireToolCallDelta>>,
+}
+
+/// A chat message in the OpenAI wire format, which LM Studio speaks too.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct WireMessage {
+    role: String,
+    // null on assistant messages that only call tools
+    #[serde(default)]
+    content: Option<WireContent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+// Plain text, or text and image parts when the message carries images
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum WireContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ImageUrl {
+    url: String,
+}
+
+impl From<WireContent> for String {
+    fn from(content: WireContent) -> Self {
+        match content {
+            WireContent::Text(text) => text,
+            WireContent::Parts(parts) => parts.into_iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WireToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    function: WireFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WireFunctionCall {
+    name: String,
+    // JSON-encoded, and not always valid when the model gets it wrong
+    arguments: String,
+}
+
+impl From<Message> for WireMessage {
+    fn from(message: Message) -> Self {
+        let content = if message.images.is_empty() {
+            WireContent::Text(message.content)
+        } else {
+            let images = message.images.iter().map(|image| ContentPart::ImageUrl {
+                image_url: ImageUrl { url: images::data_url(image) },
+            });
+            WireContent::Parts(std::iter::once(ContentPart::Text { text: message.content }).chain(images).collect())
+        };
+
+        Self {
+            role: message.role,
+            content: Some(content),
+            tool_calls: message.tool_calls.into_iter().map(|call| WireToolCall {
+                id: call.id,
+                kind: "function".to_string(),
+                function: WireFunctionCall { name: call.name, arguments: call.arguments.to_string() },
+            }).collect(),
+            tool_call_id: message.tool_call_id,
+        }
+    }
+}
+
+impl From<WireMessage> for Message {
+    fn from(message: WireMessage) -> Self {
+        Self {
+            role: message.role,
+            content: message.content.map(String::from).unwrap_or_default(),
+            tool_calls: message.tool_calls.into_iter().map(|call| ToolCall {
+                id: call.id,
+                name: call.function.name,
+                arguments: parse_arguments(call.function.arguments),
+            }).collect(),
+            tool_call_id: message.tool_call_id,
+            ..Self::default()
+        }
+    }
+}
+
+// Unparseable arguments are passed on as a string so the tool can report
+// the problem back to the model
+fn parse_arguments(arguments: String) -> serde_json::Value {
+    if arguments.trim().is_empty() {
+        return serde_json::Value::Object(Default::default());
+    }
+    serde_json::from_str(&arguments).unwrap_or(serde_json::Value::String(arguments))
+}
+
+/// Fragment of a streamed tool call; the first fragment for an index
+/// carries the id and name, the rest append to the arguments.
+#[derive(Debug, Deserialize)]
+pub(super) struct WireToolCallDelta {
+    index: usize,
+    id: Option<String>,
+    function: Option<WireFunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WireFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// `/embeddings` request body, which LM Studio takes too.
+#[derive(Debug, Serialize)]
+pub(super) struct EmbeddingsRequest<'a> {
+    pub(super) model: &'a str,
+    pub(super) input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct EmbeddingsResponse {
+    model: String,
+    data: Vec<EmbeddingData>,
+    usage: Option<EmbeddingsUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+}
+
+impl EmbeddingsResponse {
+    // Vectors are matched to inputs by index, not by position in `data`
+    pub(super) fn into_response(mut self) -> LLMResult<EmbeddingResponse> {
+        self.data.sort_by_key(|d| d.index);
+        let usage = TokenUsage::new(self.usage.map_or(0, |u| u.prompt_tokens), 0);
+        EmbeddingResponse::new(self.model, self.data.into_iter().map(|d| d.embedding).collect(), usage)
+    }
+}
+
+/// Assembles tool calls from streamed fragments.
+#[derive(Debug, Default)]
+pub(super) struct ToolCallDeltas {
+    // (id, name, arguments) by index
+    calls: Vec<(String, String, String)>,
+}
+
+impl ToolCallDeltas {
+    pub(super) fn push(&mut self, deltas: Vec<WireToolCallDelta>) {
+        for delta in deltas {
+            if self.calls.len() <= delta.index {
+                self.calls.resize_with(delta.index + 1, Default::default);
+            }
+            let (id, name, arguments) = &mut self.calls[delta.index];
+            if let Some(delta_id) = delta.id {
+                *id = delta_id;
+            }
+            if let Some(function) = delta.function {
+                name.push_str(&function.name.unwrap_or_default());
+                arguments.push_str(&function.arguments.unwrap_or_default());
+            }
+        }
+    }
+
+    pub(super) fn finish(self) -> Vec<ToolCall> {
+        self.calls.into_iter()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, arguments)| ToolCall { id, name, arguments: parse_arguments(arguments) })
+            .collect()
+    }
+}
+
+impl OpenAICompatibleClient {
+    pub fn new(config: OpenAICompatibleConfig) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(120))
+            .connect_timeout(Duration::from_secs(5))
+            .default_headers(default_headers(&config)?)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn chat_request(request: ChatRequest, stream: bool) -> OpenAIChatRequest {
+        OpenAIChatRequest {
+            model: request.model,
+            messages: request.messages.into_iter().map(WireMessage::from).collect(),
+            sampling: request.sampling,
+            response_format: request.schema.map(ResponseFormat::json_schema),
+            tools: request.tools.into_iter().map(FunctionTool::from).collect(),
+            stream,
+            stream_options: stream.then_some(StreamOptions { include_usage: true }),
+        }
+    }
+
+    async fn post_chat(&self, request: &OpenAIChatRequest) -> LLMResult<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut builder = self.client.post(&url).json(request);
+        if request.stream {
+            builder = builder.timeout(STREAM_TIMEOUT);
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Chat failed", Some(&request.model), status, error_text));
+        }
+
+        Ok(response)
+    }
+}
+
+fn default_headers(config: &OpenAICompatibleConfig) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    if let Some(api_key) = &config.api_key {
+        let header_name = config.api_key_header.as_deref().unwrap_or("Authorization");
+        let name = HeaderName::from_bytes(header_name.as_bytes())
+            .with_context(|| format!("Invalid API key header name: {}", header_name))?;
+        let value = if name == AUTHORIZATION {
+            format!("Bearer {}", api_key)
+        } else {
+            api_key.clone()
+        };
+        let mut value = HeaderValue::from_str(&value).context("Invalid API key")?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    if let Some(organization) = &config.organization {
+        headers.insert(
+            "OpenAI-Organization",
+            HeaderValue::from_str(organization).context("Invalid organization id")?,
+        );
+    }
+
+    Ok(headers)
+}
+
+#[async_trait::async_trait]
+impl LLMClient for OpenAICompatibleClient {
+    fn provider(&self) -> LLMProvider {
+        LLMProvider::OpenAI
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn list_models(&self) -> LLMResult<Vec<ModelInfo>> {
+        let url = format!("{}/models", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Failed to list models", None, status, error_text));
+        }
+
+        let models_response: OpenAIModelsResponse = response.json().await?;
+
+        let models = models_response.data.into_iter().map(|m| {
+            let name = m.id.rsplit('/').next().unwrap_or(&m.id).to_string();
+            ModelInfo {
+                id: m.id,
+                name,
+                size: None,
+                provider: LLMProvider::OpenAI,
+                provider_id: String::new(),
+                status: ModelStatus::Loaded,
+                performance: None,
+                context_length: None, // Not part of the OpenAI models schema
+                quantization: None,
+                metadata: ModelMetadata::default(),
+            }
+        }).collect();
+
+        Ok(models)
+    }
+
+    // Plain prompts go through chat completions since many hosted endpoints
+    // no longer serve the legacy /completions route
+    async fn generate(&self, request: GenerateRequest) -> LLMResult<GenerateResponse> {
+        self.chat(request.into()).await.map(GenerateResponse::from)
+    }
+
+    async fn chat(&self, request: ChatRequest) -> LLMResult<ChatResponse> {
+        let openai_request = Self::chat_request(request, false);
+
+        let start_time = Instant::now();
+        let response = self.post_chat(&openai_request).await?;
+        let openai_response: OpenAIChatResponse = response.json().await?;
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+
+        let (message, finish_reason) = openai_response.choices.into_iter().next()
+            .map(|c| (Message::from(c.message), FinishReason::from_provider(c.finish_reason.as_deref())))
+            .unwrap_or_else(|| (Message::assistant(""), FinishReason::Unknown));
+
+        let usage = openai_response.usage.map(TokenUsage::from).unwrap_or_default();
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), None);
+
+        Ok(ChatResponse {
+            message,
+            model: openai_response.model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second: timings.tokens_per_second(tokens_generated),
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn embed(&self, model: &str, inputs: &[String]) -> LLMResult<EmbeddingResponse> {
+        let url = format!("{}/embeddings", self.base_url);
+        let response = self.client.post(&url).json(&EmbeddingsRequest { model, input: inputs }).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            return Err(LLMError::from_status("Embedding failed", Some(model), status, error_text));
+        }
+
+        let embeddings: EmbeddingsResponse = response.json().await?;
+        embeddings.into_response()
+    }
+
+    async fn generate_stream(&self, request: GenerateRequest, on_token: TokenCallback) -> LLMResult<GenerateResponse> {
+        self.chat_stream(request.into(), on_token).await.map(GenerateResponse::from)
+    }
+
+    async fn chat_stream(&self, request: ChatRequest, on_token: TokenCallback) -> LLMResult<ChatResponse> {
+        let mut model = request.model.clone();
+        let openai_request = Self::chat_request(request, true);
+
+        let start_time = Instant::now();
+        let response = self.post_chat(&openai_request).await?;
+
+        let mut content = String::new();
+        let mut chunks_received = 0;
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut tool_calls = ToolCallDeltas::default();
+        let mut time_to_first_token = None;
+
+        for_each_line(response, |line| {
+            let data = match parse_sse_line(line) {
+                Some(SseData::Data(data)) => data,
+                Some(SseData::Done) => return Ok(false),
+                None => return Ok(true),
+            };
+            let chunk: OpenAIChatChunk = serde_json::from_str(data)?;
+            if let Some(choice) = chunk.choices.into_iter().next() {
+                if let Some(token) = choice.delta.content.as_deref().filter(|t| !t.is_empty()) {
+                    time_to_first_token.get_or_insert_with(|| start_time.elapsed());
+                    on_token(token);
+                    content.push_str(token);
+                    chunks_received += 1;
+                }
+                if let Some(deltas) = choice.delta.tool_calls {
+                    tool_calls.push(deltas);
+                }
+                finish_reason = choice.finish_reason.or(finish_reason.take());
+            }
+            model = chunk.model;
+            usage = chunk.usage.or(usage.take());
+            Ok(true)
+        }).await?;
+
+        let generation_time_ms = start_time.elapsed().as_millis() as u64;
+
+        // Not every server honours stream_options, so count chunks as a fallback
+        let usage = usage.map(TokenUsage::from).unwrap_or(TokenUsage::new(0, chunks_received));
+        let tokens_generated = usage.completion_tokens;
+        let timings = GenerationTimings::measured(start_time.elapsed(), time_to_first_token);
+        let finish_reason = FinishReason::from_provider(finish_reason.as_deref());
+
+        Ok(ChatResponse {
+            message: Message { tool_calls: tool_calls.finish(), ..Message::assistant(&content) },
+            model,
+            provider_id: String::new(),
+            tokens_generated,
+            generation_time_ms,
+            tokens_per_second: timings.tokens_per_second(tokens_generated),
+            timings,
+            usage,
+            finish_reason,
+            cache_hit: false,
+        })
+    }
+
+    async fn health_check(&self) -> LLMResult<bool> {
+        let url = format!("{}/models", self.base_url);
+
+        let response = self.client.get(&url).send().await?;
+        Ok(response.status().is_success())
+    }
+
+    async fn get_model_info(&self, model_id: &str) -> LLMResult<ModelInfo> {
+        let models = self.list_models().await?;
+        models.into_iter()
+            .find(|m| m.id == model_id)
+            .ok_or_else(|| LLMError::ModelNotFound {
+                model: model_id.to_string(),
+                message: "Not in the provider's model list".to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Serves a single canned HTTP response and hands back the raw request
+    async fn mock_server(content_type: &'static str, body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text.lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        (base_url, handle)
+    }
+
+    fn config(base_url: String) -> OpenAICompatibleConfig {
+        OpenAICompatibleConfig {
+            base_url,
+            api_key: Some("sk-test".to_string()),
+            api_key_header: None,
+            organization: Some("org-forge".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_models_sends_credentials() {
+        let (base_url, server) = mock_server(
+            "application/json",
+            r#"{"object":"list","data":[{"id":"Qwen/Qwen2.5-Coder-7B-Instruct","object":"model"}]}"#,
+        ).await;
+        let client = OpenAICompatibleClient::new(config(base_url)).unwrap();
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "Qwen2.5-Coder-7B-Instruct");
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("get /v1/models"));
+        assert!(request.contains("authorization: bearer sk-test"));
+        assert!(request.contains("openai-organization: org-forge"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_api_key_header() {
+        let (base_url, server) = mock_server("application/json", r#"{"data":[]}"#).await;
+        let client = OpenAICompatibleClient::new(OpenAICompatibleConfig {
+            api_key_header: Some("x-api-key".to_string()),
+            ..config(base_url)
+        }).unwrap();
+
+        client.list_models().await.unwrap();
+
+        let request = server.await.unwrap().to_ascii_lowercase();
+        assert!(request.contains("x-api-key: sk-test"));
+        assert!(!request.contains("authorization:"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_parses_sse() {
+        let (base_url, server) = mock_server(
+            "text/event-stream",
+            "data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+             data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"content\":\"<Button\"}}]}\n\n\
+             data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"content\":\" />\"}}]}\n\n\
+             data: {\"model\":\"m\",\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2,\"total_tokens\":7}}\n\n\
+             data: [DONE]\n\n",
+        ).await;
+        let client = OpenAICompatibleClient::new(config(base_url)).unwrap();
+
+        let tokens = Arc::new(Mutex::new(Vec::new()));
+        let sink = tokens.clone();
+        let on_token: TokenCallback = Arc::new(move |t: &str| sink.lock().unwrap().push(t.to_string()));
+
+        let request = ChatRequest {
+            model: "m".to_string(),
+            messages: vec![Message::user("button")],
+            sampling: SamplingOptions::default(),
+            schema: None,
+            tools: vec![],
+            stream: true,
+            schedule: None,
+        };
+        let response = client.chat_stream(request, on_token).await.unwrap();
+
+        assert_eq!(response.message.content, "<Button />");
+        assert_eq!(response.tokens_generated, 2);
+        assert_eq!(*tokens.lock().unwrap(), vec!["<Button", " />"]);
+        assert!(server.await.unwrap().contains("\"stream\":true"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_assembles_tool_calls() {
+        let (base_url, server) = mock_server(
+            "text/event-stream",
+            "data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"function\":{\"name\":\"read_component\",\"arguments\":\"\"}}]}}]}\n\n\
+             data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"name\\\":\"}}]}}]}\n\n\
+             data: {\"model\":\"m\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"Button\\\"}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n\
+             data: [DONE]\n\n",
+        ).await;
+        let client = OpenAICompatibleClient::new(config(base_url)).unwrap();
+
+        let request = ChatRequest {
+            model: "m".to_string(),
+            messages: vec![Message::user("show me the button")],
+            sampling: SamplingOptions::default(),
+            schema: None,
+            tools: vec![ToolDefinition {
+                name: "read_component".to_string(),
+                description: "Read a component".to_string(),
+                parameters: serde_json::json!({ "type": "object" }),
+            }],
+            stream: true,
+            schedule: None,
+        };
+        let response = client.chat_stream(request, Arc::new(|_: &str| {})).await.unwrap();
+
+        assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+        let call = &response.message.tool_calls[0];
+        assert_eq!((call.id.as_str(), call.name.as_str()), ("call_a", "read_component"));
+        assert_eq!(call.arguments, serde_json::json!({ "name": "Button" }));
+        assert!(server.await.unwrap().contains("\"tools\":[{\"type\":\"function\""));
+    }
+
+    #[test]
+    fn test_images_are_sent_as_content_parts() {
+        let message = Message { images: vec!["iVBORw0KGgo".to_string()], ..Message::user("Build this") };
+        assert_eq!(serde_json::to_value(WireMessage::from(message)).unwrap(), serde_json::json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "Build this" },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo" } }
+            ]
+        }));
+
+        let plain = serde_json::to_value(WireMessage::from(Message::user("hi"))).unwrap();
+        assert_eq!(plain["content"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_embed_orders_vectors_by_index() {
+        let (base_url, server) = mock_server(
+            "application/json",
+            r#"{"model":"text-embedding-3-small","data":[{"index":1,"embedding":[0.0,1.0]},{"index":0,"embedding":[1.0,0.0]}],"usage":{"prompt_tokens":4,"total_tokens":4}}"#,
+        ).await;
+        let client = OpenAICompatibleClient::new(config(base_url)).unwrap();
+
+        let response = client.embed("text-embedding-3-small", &["a".to_string(), "b".to_string()]).await.unwrap();
+        assert_eq!(response.embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert_eq!(response.dimensions, 2);
+        assert_eq!(response.usage.prompt_tokens, 4);
+        assert!(server.await.unwrap().starts_with("POST /v1/embeddings"));
+    }
+}
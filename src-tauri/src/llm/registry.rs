@@ -0,0 +1,139 @@
+use super::types::*;
+use super::ollama::OllamaClient;
+use super::lmstudio::LMStudioClient;
+use super::openai::{OpenAICompatibleClient, OpenAICompatibleConfig};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Everything needed to construct a client for one provider instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub id: String,
+    pub provider: LLMProvider,
+    pub base_url: String,
+    // Only used by OpenAI-compatible endpoints
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_header: Option<String>,
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+impl ProviderConfig {
+    pub fn new(id: &str, provider: LLMProvider, base_url: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            provider,
+            base_url: base_url.to_string(),
+            api_key: None,
+            api_key_header: None,
+            organization: None,
+        }
+    }
+
+    pub fn build_client(&self) -> Result<Arc<dyn LLMClient>> {
+        let client: Arc<dyn LLMClient> = match self.provider {
+            LLMProvider::Ollama => Arc::new(OllamaClient::new(self.base_url.clone())),
+            LLMProvider::LMStudio => Arc::new(LMStudioClient::new(self.base_url.clone())),
+            LLMProvider::OpenAI => Arc::new(OpenAICompatibleClient::new(OpenAICompatibleConfig {
+                base_url: self.base_url.clone(),
+                api_key: self.api_key.clone(),
+                api_key_header: self.api_key_header.clone(),
+                organization: self.organization.clone(),
+            })?),
+        };
+        Ok(client)
+    }
+}
+
+/// Summary of a registered provider for the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub provider: LLMProvider,
+    pub base_url: String,
+}
+
+/// Registered clients keyed by provider id. Registration order doubles as
+/// the preference order when falling back between providers.
+#[derive(Default, Clone)]
+pub struct ProviderRegistry {
+    entries: Vec<(String, Arc<dyn LLMClient>)>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a client, replacing any existing client with the same id in place.
+    pub fn insert(&mut self, id: &str, client: Arc<dyn LLMClient>) {
+        match self.entries.iter_mut().find(|(existing, _)| existing == id) {
+            Some(entry) => entry.1 = client,
+            None => self.entries.push((id.to_string(), client)),
+        }
+    }
+
+    /// Looks up a provider by id, accepting legacy spellings like "LM Studio".
+    pub fn get(&self, id: &str) -> Option<(&str, &Arc<dyn LLMClient>)> {
+        let normalized = normalize_id(id);
+        self.entries.iter()
+            .find(|(existing, _)| existing == id)
+            .or_else(|| self.entries.iter().find(|(existing, _)| normalize_id(existing) == normalized))
+            .map(|(id, client)| (id.as_str(), client))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<dyn LLMClient>)> {
+        self.entries.iter().map(|(id, client)| (id.as_str(), client))
+    }
+
+    pub fn list(&self) -> Vec<ProviderInfo> {
+        self.iter().map(|(id, client)| ProviderInfo {
+            id: id.to_string(),
+            provider: client.provider(),
+            base_url: client.base_url().to_string(),
+        }).collect()
+    }
+}
+
+fn normalize_id(id: &str) -> String {
+    id.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(base_url: &str) -> Arc<dyn LLMClient> {
+        ProviderConfig::new("unused", LLMProvider::Ollama, base_url).build_client().unwrap()
+    }
+
+    #[test]
+    fn test_registry_supports_multiple_instances_of_a_backend() {
+        let mut registry = ProviderRegistry::new();
+        registry.insert("ollama", client("http://localhost:11434"));
+        registry.insert("ollama-gpu-box", client("http://10.0.0.5:11434"));
+
+        let providers = registry.list();
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[1].base_url, "http://10.0.0.5:11434");
+
+        registry.insert("ollama", client("http://localhost:11500"));
+        assert_eq!(registry.list()[0].base_url, "http://localhost:11500");
+        assert_eq!(registry.list().len(), 2);
+    }
+
+    #[test]
+    fn test_lookup_accepts_legacy_names() {
+        let mut registry = ProviderRegistry::new();
+        registry.insert("lmstudio", client("http://localhost:1234"));
+
+        assert_eq!(registry.get("LM Studio").map(|(id, _)| id), Some("lmstudio"));
+        assert!(registry.get("ollama").is_none());
+    }
+}
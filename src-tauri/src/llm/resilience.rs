@@ -0,0 +1,199 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often and how patiently a transient failure is retried against the
+/// same provider before falling back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts per provider, including the first one
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// Fraction of the backoff randomly added or removed, 0.0 to 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1 for the first retry).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = (self.initial_backoff_ms as f64 * self.multiplier.powi(exponent))
+            .min(self.max_backoff_ms as f64);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+
+        Duration::from_millis((base * factor) as u64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive transient failures before the provider is skipped
+    pub failure_threshold: u32,
+    /// How long an open circuit waits before letting a trial request through
+    pub cooldown_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Per-provider circuit breaker. After `failure_threshold` consecutive
+/// failures the provider is skipped for `cooldown_ms`, then a single trial
+/// request decides whether it closes again.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown() => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent now. In the half-open state only one
+    /// trial request is let through per cooldown period.
+    pub fn allow_request(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(opened_at) = inner.opened_at else {
+            return true;
+        };
+
+        if opened_at.elapsed() < self.cooldown() {
+            return false;
+        }
+
+        // A trial that never reported back (e.g. it was cancelled) shouldn't
+        // keep the circuit stuck
+        match inner.trial_started {
+            Some(started) if started.elapsed() < self.cooldown() => false,
+            _ => {
+                inner.trial_started = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.inner.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        inner.trial_started = None;
+
+        let trial_failed = inner.opened_at.is_some();
+        if trial_failed || inner.consecutive_failures >= self.config.failure_threshold.max(1) {
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn cooldown(&self) -> Duration {
+        Duration::from_millis(self.config.cooldown_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_within_jitter_and_cap() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 1..=3 {
+            let expected = 500.0 * 2f64.powi(attempt as i32 - 1);
+            let delay = policy.backoff(attempt).as_millis() as f64;
+            assert!(delay >= expected * 0.8 && delay <= expected * 1.2, "attempt {}: {}", attempt, delay);
+        }
+
+        assert!(policy.backoff(20).as_millis() <= 8_000 * 12 / 10);
+    }
+
+    #[test]
+    fn test_breaker_opens_then_half_opens_after_cooldown() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig { failure_threshold: 2, cooldown_ms: 20 });
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow_request());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow_request());
+        // Only one trial at a time
+        assert!(!breaker.allow_request());
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow_request());
+    }
+
+    #[test]
+    fn test_failed_trial_reopens_circuit() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig { failure_threshold: 1, cooldown_ms: 20 });
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(30));
+
+        assert!(breaker.allow_request());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+}
@@ -1,149 +1,834 @@
 use super::types::*;
-use super::ollama::OllamaClient;
-use super::lmstudio::LMStudioClient;
-use anyhow::Result;
-use std::sync::Arc;
+use super::error::{LLMError, LLMResult};
+use super::models::{find_equivalent, is_embedding_model};
+use super::capabilities::{capabilities_for, Capability, ServerCapabilities};
+use super::downloads::Downloads;
+use super::registry::{ProviderConfig, ProviderInfo, ProviderRegistry};
+use super::resilience::{CircuitBreaker, CircuitBreakerConfig, CircuitState, RetryPolicy};
+use super::sampling::SamplingOptions;
+use super::scheduler::Schedule;
+use super::stream::{PullProgressCallback, TokenCallback};
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Inputs per embeddings request; servers cap batch sizes, and a single huge
+// request holds the model for a long time
+const EMBED_BATCH_SIZE: usize = 64;
+
+/// Routes requests to providers. Cloning is cheap and clones share clients,
+/// circuit breakers and downloads, so settings changes are applied to a copy
+/// that replaces the router readers see.
+#[derive(Clone)]
 pub struct LLMRouter {
-    ollama_client: Arc<OllamaClient>,
-    lmstudio_client: Arc<LMStudioClient>,
+    providers: ProviderRegistry,
+    configs: HashMap<String, ProviderConfig>,
+    fallback_chain: Vec<String>,
+    retry_policy: RetryPolicy,
+    breaker_config: CircuitBreakerConfig,
+    breakers: HashMap<String, Arc<CircuitBreaker>>,
+    downloads: Arc<Downloads>,
+    // Server versions seen by detect_servers, keyed by provider id
+    versions: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl LLMRouter {
     pub fn new() -> Self {
         Self {
-            ollama_client: Arc::new(OllamaClient::new("http://localhost:11434".to_string())),
-            lmstudio_client: Arc::new(LMStudioClient::new("http://localhost:1234".to_string())),
+            providers: ProviderRegistry::new(),
+            configs: HashMap::new(),
+            fallback_chain: vec![],
+            retry_policy: RetryPolicy::default(),
+            breaker_config: CircuitBreakerConfig::default(),
+            breakers: HashMap::new(),
+            downloads: Arc::new(Downloads::new()),
+            versions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Sets the provider ids tried, in order, after the requested provider
+    /// fails. An empty chain falls back through every local provider.
+    pub fn set_fallback_chain(&mut self, chain: Vec<String>) {
+        self.fallback_chain = chain;
+    }
+
+    /// Changing the breaker config resets every provider's circuit.
+    pub fn set_resilience(&mut self, retry_policy: RetryPolicy, breaker_config: CircuitBreakerConfig) {
+        self.retry_policy = retry_policy;
+        if breaker_config != self.breaker_config {
+            for breaker in self.breakers.values_mut() {
+                *breaker = Arc::new(CircuitBreaker::new(breaker_config.clone()));
+            }
+            self.breaker_config = breaker_config;
+        }
+    }
+
+    /// Makes the registry match `configs`, in order. Clients whose config is
+    /// unchanged are kept as-is; everything else is rebuilt or dropped. On
+    /// error the current providers are left untouched.
+    pub fn apply_provider_configs(&mut self, configs: &[ProviderConfig]) -> Result<()> {
+        let mut providers = ProviderRegistry::new();
+        let mut breakers = HashMap::new();
+
+        for config in configs {
+            let existing = self.providers.iter()
+                .find(|(id, _)| *id == config.id)
+                .filter(|_| self.configs.get(&config.id) == Some(config))
+                .map(|(_, client)| client.clone());
+
+            let client = match existing {
+                Some(client) => client,
+                None => config.build_client()
+                    .with_context(|| format!("Failed to configure provider {}", config.id))?,
+            };
+            providers.insert(&config.id, client);
+
+            // A rebuilt client starts with a fresh circuit
+            let breaker = match self.breakers.remove(&config.id) {
+                Some(breaker) if self.configs.get(&config.id) == Some(config) => breaker,
+                _ => Arc::new(CircuitBreaker::new(self.breaker_config.clone())),
+            };
+            breakers.insert(config.id.clone(), breaker);
         }
+
+        // Rebuilt providers may point at a different server
+        self.versions.lock().unwrap()
+            .retain(|id, _| configs.iter().any(|c| c.id == *id && self.configs.get(id) == Some(c)));
+
+        self.providers = providers;
+        self.breakers = breakers;
+        self.configs = configs.iter().map(|c| (c.id.clone(), c.clone())).collect();
+        Ok(())
+    }
+
+    pub fn list_providers(&self) -> Vec<ProviderInfo> {
+        self.providers.list()
     }
 
+    /// Probes every provider concurrently, so one unreachable host can't
+    /// hold up the others.
     pub async fn detect_servers(&self) -> Result<ServerStatus> {
-        let ollama_connected = self.ollama_client.health_check().await.unwrap_or(false);
-        let lmstudio_connected = self.lmstudio_client.health_check().await.unwrap_or(false);
+        let probes = self.providers.iter().map(|(id, client)| async move {
+            (id.to_string(), self.probe(id, client.as_ref()).await)
+        });
 
-        let ollama_models = if ollama_connected {
-            self.ollama_client.list_models().await
-                .map(|models| models.iter().map(|m| m.id.clone()).collect())
-                .unwrap_or_default()
+        Ok(join_all(probes).await.into_iter().collect())
+    }
+
+    async fn probe(&self, id: &str, client: &dyn LLMClient) -> ServerConnectionStatus {
+        let started = Instant::now();
+        let health = with_probe_timeout(client.health_check()).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let (connected, mut error) = match &health {
+            Ok(true) => (true, None),
+            Ok(false) => (false, Some("Server responded with an error status".to_string())),
+            Err(e) => (false, Some(e.to_string())),
+        };
+
+        let models_loaded = if connected {
+            match with_probe_timeout(client.list_models()).await {
+                Ok(models) => models.into_iter().map(|m| m.id).collect(),
+                Err(e) => {
+                    error = Some(format!("Failed to list models: {}", e));
+                    vec![]
+                }
+            }
         } else {
             vec![]
         };
 
-        let lmstudio_models = if lmstudio_connected {
-            self.lmstudio_client.list_models().await
-                .map(|models| models.iter().map(|m| m.id.clone()).collect())
-                .unwrap_or_default()
+        let version = if connected {
+            with_probe_timeout(client.server_version()).await.unwrap_or_else(|e| {
+                tracing::debug!("Could not get version of provider {}: {}", id, e);
+                None
+            })
         } else {
-            vec![]
+            None
         };
+        if let Some(version) = &version {
+            self.versions.lock().unwrap().insert(id.to_string(), version.clone());
+        }
 
-        Ok(ServerStatus {
-            ollama: ServerConnectionStatus {
-                connected: ollama_connected,
-                version: None,
-                models_loaded: ollama_models,
-                error: if !ollama_connected { 
-                    Some("Ollama server not running. Start with: ollama serve".to_string()) 
-                } else { 
-                    None 
-                },
+        ServerConnectionStatus {
+            provider: client.provider(),
+            base_url: client.base_url().to_string(),
+            connected,
+            capabilities: capabilities_for(client.provider(), version.as_deref()),
+            version,
+            models_loaded,
+            latency_ms: health.is_ok().then_some(latency_ms),
+            error,
+            hint: if !connected {
+                Some(offline_hint(&client.provider()).to_string())
+            } else {
+                None
             },
-            lmstudio: ServerConnectionStatus {
-                connected: lmstudio_connected,
-                version: None,
-                models_loaded: lmstudio_models,
-                error: if !lmstudio_connected { 
-                    Some("LM Studio server not running. Start LM Studio and enable server mode.".to_string()) 
-                } else { 
-                    None 
-                },
-            },
-        })
+            circuit: self.breaker(id).state(),
+        }
     }
 
-    pub async fn list_all_models(&self) -> Result<Vec<ModelInfo>> {
-        let mut all_models = vec![];
+    /// Models of one kind across every provider; embedding models can't
+    /// generate text, so they're listed separately.
+    pub async fn list_all_models(&self, kind: ModelKind) -> Result<Vec<ModelInfo>> {
+        let listings = self.providers.iter().map(|(id, client)| async move {
+            match with_probe_timeout(client.list_models()).await {
+                Ok(models) => models.into_iter().map(|mut model| {
+                    model.provider_id = id.to_string();
+                    model
+                }).collect(),
+                Err(e) => {
+                    tracing::debug!("Skipping models from provider {}: {}", id, e);
+                    vec![]
+                }
+            }
+        });
+
+        let mut models: Vec<ModelInfo> = join_all(listings).await.into_iter()
+            .flatten()
+            .filter(|model| is_embedding_model(model) == (kind == ModelKind::Embedding))
+            .collect();
 
-        // Get Ollama models
-        if let Ok(models) = self.ollama_client.list_models().await {
-            all_models.extend(models);
+        // Models being pulled show up as downloading, including re-pulls of
+        // models the provider already lists
+        for downloading in self.downloads.models() {
+            match models.iter_mut().find(|m| m.provider_id == downloading.provider_id && m.id == downloading.id) {
+                Some(existing) => existing.status = ModelStatus::Downloading,
+                None => models.push(downloading),
+            }
         }
 
-        // Get LM Studio models
-        if let Ok(models) = self.lmstudio_client.list_models().await {
-            all_models.extend(models);
+        Ok(models)
+    }
+
+    /// The client for a provider, for one-off calls that shouldn't hold the
+    /// router while they run.
+    pub fn client(&self, provider: &str) -> LLMResult<Arc<dyn LLMClient>> {
+        self.resolve(provider).map(|(_, client)| client)
+    }
+
+    /// The registered id for `provider`, which may be a legacy spelling like
+    /// "LM Studio", along with its client.
+    pub fn resolve(&self, provider: &str) -> LLMResult<(String, Arc<dyn LLMClient>)> {
+        self.providers.get(provider)
+            .map(|(id, client)| (id.to_string(), client.clone()))
+            .ok_or_else(|| LLMError::validation(format!("Unknown provider: {}", provider)))
+    }
+
+    /// The provider a request for `provider` goes to first: the provider
+    /// itself if registered, otherwise the head of the fallback chain.
+    pub fn primary_provider(&self, provider: &str) -> Option<String> {
+        self.fallback_order(provider).first().map(|(id, _)| id.to_string())
+    }
+
+    /// Digest of a model's weights, for providers that report one, so
+    /// results can be told apart when a tag is re-pulled.
+    pub async fn model_digest(&self, provider: &str, model: &str) -> Option<String> {
+        let client = self.client(provider).ok()?;
+        let models = with_probe_timeout(client.list_models()).await.ok()?;
+        models.into_iter().find(|m| m.id == model)?.metadata.digest
+    }
+
+    /// Models resident in memory across every provider that can report them.
+    pub async fn running_models(&self) -> Vec<RunningModel> {
+        let listings = self.providers.iter().map(|(id, client)| async move {
+            match with_probe_timeout(client.running_models()).await {
+                Ok(models) => models.into_iter().map(|mut model| {
+                    model.provider_id = id.to_string();
+                    model
+                }).collect(),
+                Err(e) => {
+                    tracing::debug!("No running models from provider {}: {}", id, e);
+                    vec![]
+                }
+            }
+        });
+
+        join_all(listings).await.into_iter().flatten().collect()
+    }
+
+    /// Starts downloading `model` on `provider`, or on the first Ollama
+    /// provider if none is given. The returned future doesn't borrow the
+    /// router, so callers can release their lock while the download runs.
+    pub fn pull_model(
+        &self,
+        provider: Option<&str>,
+        model: &str,
+        on_progress: PullProgressCallback,
+    ) -> LLMResult<impl Future<Output = LLMResult<()>> + Send + 'static> {
+        let (id, client) = match provider {
+            Some(provider) => self.providers.get(provider),
+            None => self.providers.iter().find(|(_, client)| client.provider() == LLMProvider::Ollama),
+        }.ok_or_else(|| LLMError::validation("No provider available to download models"))?;
+
+        let client = client.clone();
+        let guard = self.downloads.start(id, client.provider(), model);
+        let model = model.to_string();
+
+        Ok(async move {
+            let on_progress: PullProgressCallback = Arc::new(move |progress| {
+                guard.update(progress);
+                on_progress(progress);
+            });
+            client.pull_model(&model, on_progress).await
+        })
+    }
+
+    /// Embeds `inputs` in batches with one model on one provider. There's no
+    /// fallback, since vectors from different models can't be compared.
+    pub async fn embed(&self, provider: &str, model: &str, inputs: &[String]) -> LLMResult<EmbeddingResponse> {
+        let client = self.client(provider)?;
+        if !self.capabilities(provider, client.as_ref()).supports(Capability::Embeddings) {
+            return Err(client.unsupported("compute embeddings"));
         }
 
-        Ok(all_models)
+        let mut response = EmbeddingResponse { model: model.to_string(), ..EmbeddingResponse::default() };
+        for batch in inputs.chunks(EMBED_BATCH_SIZE) {
+            let next = client.embed(model, batch).await?;
+            if next.embeddings.len() != batch.len() {
+                return Err(LLMError::Decode {
+                    message: format!("Expected {} embeddings, got {}", batch.len(), next.embeddings.len()),
+                });
+            }
+            if !response.embeddings.is_empty() && next.dimensions != response.dimensions {
+                return Err(LLMError::Decode { message: "Embedding dimensions changed between batches".to_string() });
+            }
+
+            response.model = next.model;
+            response.dimensions = next.dimensions;
+            response.embeddings.extend(next.embeddings);
+            response.usage = response.usage + next.usage;
+        }
+        response.provider_id = provider.to_string();
+        Ok(response)
     }
 
     pub async fn generate_with_fallback(
         &self,
         provider: &str,
         request: GenerateRequest,
-    ) -> Result<GenerateResponse> {
-        match provider.to_lowercase().as_str() {
-            "ollama" => {
-                match self.ollama_client.generate(request.clone()).await {
-                    Ok(response) => Ok(response),
-                    Err(e) => {
-                        // Try LM Studio as fallback
-                        tracing::warn!("Ollama failed, trying LM Studio: {}", e);
-                        self.lmstudio_client.generate(request).await
+    ) -> LLMResult<GenerateResponse> {
+        let (mut response, provider_id) = self.with_fallback(provider, &request.model, &request.sampling, &required(&[], request.schema.is_some()), request.schedule.as_ref(), || true, |client, model| {
+            let request = GenerateRequest { model, ..request.clone() };
+            async move { client.generate(request).await }
+        }).await?;
+        response.provider_id = provider_id;
+        Ok(response)
+    }
+
+    pub async fn chat_with_fallback(
+        &self,
+        provider: &str,
+        request: ChatRequest,
+    ) -> LLMResult<ChatResponse> {
+        let (mut response, provider_id) = self.with_fallback(provider, &request.model, &request.sampling, &chat_required(&[Capability::Chat], &request), request.schedule.as_ref(), || true, |client, model| {
+            let request = ChatRequest { model, ..request.clone() };
+            async move { client.chat(request).await }
+        }).await?;
+        response.provider_id = provider_id;
+        Ok(response)
+    }
+
+    pub async fn generate_stream_with_fallback(
+        &self,
+        provider: &str,
+        request: GenerateRequest,
+        on_token: TokenCallback,
+    ) -> LLMResult<GenerateResponse> {
+        let (on_token, streamed) = track_tokens(on_token);
+
+        // Once tokens have reached the UI a retry would duplicate output
+        let can_retry = || !streamed.load(Ordering::SeqCst);
+        let required = required(&[Capability::Streaming], request.schema.is_some());
+        let (mut response, provider_id) = self.with_fallback(provider, &request.model, &request.sampling, &required, request.schedule.as_ref(), can_retry, |client, model| {
+            let request = GenerateRequest { model, ..request.clone() };
+            let on_token = on_token.clone();
+            async move { client.generate_stream(request, on_token).await }
+        }).await?;
+        response.provider_id = provider_id;
+        Ok(response)
+    }
+
+    pub async fn chat_stream_with_fallback(
+        &self,
+        provider: &str,
+        request: ChatRequest,
+        on_token: TokenCallback,
+    ) -> LLMResult<ChatResponse> {
+        let (on_token, streamed) = track_tokens(on_token);
+
+        let can_retry = || !streamed.load(Ordering::SeqCst);
+        let required = chat_required(&[Capability::Chat, Capability::Streaming], &request);
+        let (mut response, provider_id) = self.with_fallback(provider, &request.model, &request.sampling, &required, request.schedule.as_ref(), can_retry, |client, model| {
+            let request = ChatRequest { model, ..request.clone() };
+            let on_token = on_token.clone();
+            async move { client.chat_stream(request, on_token).await }
+        }).await?;
+        response.provider_id = provider_id;
+        Ok(response)
+    }
+
+    // Try the requested provider with the requested model, then each fallback
+    // provider with its equivalent of that model. Transient failures are
+    // retried with backoff before moving on, and providers whose circuit is
+    // open, that lack a `required` capability or that can't honour the
+    // sampling parameters are skipped. With a schedule, each provider tried
+    // is only called once the request has a slot on it. Returns the id of
+    // the provider that produced the response.
+    #[allow(clippy::too_many_arguments)]
+    async fn with_fallback<T, F, Fut>(
+        &self,
+        provider: &str,
+        model: &str,
+        sampling: &SamplingOptions,
+        required: &[Capability],
+        schedule: Option<&Schedule>,
+        can_retry: impl Fn() -> bool,
+        mut call: F,
+    ) -> LLMResult<(T, String)>
+    where
+        F: FnMut(Arc<dyn LLMClient>, String) -> Fut,
+        Fut: Future<Output = LLMResult<T>>,
+    {
+        let requested_id = self.providers.get(provider).map(|(id, _)| id);
+        let mut last_error = None;
+        let mut open_circuits = vec![];
+        let mut missing_capability = None;
+        let mut unsupported_sampling = None;
+
+        for (id, client) in self.fallback_order(provider) {
+            if last_error.is_some() && !can_retry() {
+                break;
+            }
+
+            let capabilities = self.capabilities(id, client.as_ref());
+            if let Some(missing) = required.iter().find(|c| !capabilities.supports(**c)) {
+                tracing::info!("Provider {} does not support {}, skipping", id, missing);
+                missing_capability = Some(*missing);
+                continue;
+            }
+
+            if let Err(e) = sampling.check_supported(client.provider()) {
+                // Falling back would quietly drop what the caller asked for
+                if Some(id) == requested_id {
+                    return Err(e);
+                }
+                tracing::info!("Skipping provider {}: {}", id, e);
+                unsupported_sampling = Some(e);
+                continue;
+            }
+
+            let breaker = self.breaker(id);
+            if breaker.state() == CircuitState::Open {
+                tracing::info!("Circuit open for provider {}, skipping", id);
+                open_circuits.push(id);
+                continue;
+            }
+
+            let target_model = if Some(id) == requested_id {
+                model.to_string()
+            } else {
+                match equivalent_model(client.as_ref(), model).await {
+                    Some(equivalent) => equivalent,
+                    None => {
+                        tracing::info!("Provider {} has no model equivalent to {}, skipping", id, model);
+                        continue;
                     }
                 }
-            },
-            "lmstudio" | "lm studio" => {
-                match self.lmstudio_client.generate(request.clone()).await {
-                    Ok(response) => Ok(response),
+            };
+
+            let mut vision_unknown = false;
+            if required.contains(&Capability::Vision) {
+                match model_supports(client.as_ref(), &target_model, "vision").await {
+                    Some(true) => {}
+                    Some(false) => {
+                        tracing::info!("Model {} on provider {} does not support vision, skipping", target_model, id);
+                        missing_capability = Some(Capability::Vision);
+                        continue;
+                    }
+                    None => {
+                        tracing::info!("Can't tell whether {} on provider {} supports vision, trying it", target_model, id);
+                        vision_unknown = true;
+                    }
+                }
+            }
+
+            if Some(id) != requested_id {
+                tracing::info!("Falling back to {} on provider {}", target_model, id);
+            }
+
+            let _permit = match schedule {
+                Some(schedule) => Some(schedule.acquire(id).await),
+                None => None,
+            };
+
+            let mut attempt = 0;
+            // Half-open circuits let a single trial through, so another
+            // request may already be probing this provider
+            while breaker.allow_request() {
+                attempt += 1;
+                match call(client.clone(), target_model.clone()).await {
+                    Ok(response) => {
+                        breaker.record_success();
+                        return Ok((response, id.to_string()));
+                    }
                     Err(e) => {
-                        // Try Ollama as fallback
-                        tracing::warn!("LM Studio failed, trying Ollama: {}", e);
-                        self.ollama_client.generate(request).await
+                        let transient = e.is_transient();
+                        if transient {
+                            breaker.record_failure();
+                        } else {
+                            // The server answered, so it is healthy even if
+                            // it rejected this request
+                            breaker.record_success();
+                        }
+                        tracing::warn!("Provider {} failed (attempt {}): {}", id, attempt, e);
+                        last_error = Some(match vision_unknown && rejected_request(&e) {
+                            true => LLMError::Unsupported {
+                                message: format!("{} on {} rejected the images, and its vision support can't be checked: {}", target_model, id, e),
+                            },
+                            false => e,
+                        });
+
+                        if !transient || attempt >= self.retry_policy.max_attempts || !can_retry() {
+                            break;
+                        }
+                        tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
                     }
                 }
-            },
-            _ => {
-                // Try both, prefer Ollama
-                match self.ollama_client.generate(request.clone()).await {
-                    Ok(response) => Ok(response),
-                    Err(_) => self.lmstudio_client.generate(request).await,
+            }
+        }
+
+        Err(last_error.or(unsupported_sampling).unwrap_or_else(|| {
+            if missing_capability == Some(Capability::Vision) {
+                LLMError::Unsupported {
+                    message: format!("{} does not support images and no fallback model does either", model),
+                }
+            } else if let Some(capability) = missing_capability {
+                LLMError::Unsupported {
+                    message: format!("No available provider supports {}", capability),
+                }
+            } else if !open_circuits.is_empty() {
+                LLMError::Unavailable {
+                    message: format!("Providers temporarily unavailable: {}", open_circuits.join(", ")),
                 }
+            } else {
+                LLMError::ModelNotFound {
+                    model: model.to_string(),
+                    message: "No provider can serve this model".to_string(),
+                }
+            }
+        }))
+    }
+
+    // Until detect_servers has seen a version, assume a current server
+    fn capabilities(&self, id: &str, client: &dyn LLMClient) -> ServerCapabilities {
+        let versions = self.versions.lock().unwrap();
+        capabilities_for(client.provider(), versions.get(id).map(String::as_str))
+    }
+
+    fn breaker(&self, id: &str) -> &CircuitBreaker {
+        self.breakers.get(id).expect("every registered provider has a circuit breaker")
+    }
+
+    fn fallback_order(&self, provider: &str) -> Vec<(&str, &Arc<dyn LLMClient>)> {
+        let requested = self.providers.get(provider);
+
+        // Hosted endpoints use their own model ids, so they only serve
+        // requests addressed to them
+        if let Some((_, client)) = requested {
+            if client.provider() == LLMProvider::OpenAI {
+                return requested.into_iter().collect();
             }
         }
+
+        let fallbacks: Vec<(&str, &Arc<dyn LLMClient>)> = if self.fallback_chain.is_empty() {
+            self.providers.iter()
+                .filter(|(_, client)| client.provider() != LLMProvider::OpenAI)
+                .collect()
+        } else {
+            self.fallback_chain.iter()
+                .filter_map(|id| self.providers.get(id))
+                .collect()
+        };
+
+        requested.into_iter()
+            .chain(fallbacks.into_iter().filter(|(id, _)| Some(*id) != requested.map(|(r, _)| r)))
+            .collect()
     }
+}
 
-    pub async fn chat_with_fallback(
-        &self,
-        provider: &str,
-        request: ChatRequest,
-    ) -> Result<ChatResponse> {
-        match provider.to_lowercase().as_str() {
-            "ollama" => {
-                match self.ollama_client.chat(request.clone()).await {
-                    Ok(response) => Ok(response),
-                    Err(e) => {
-                        tracing::warn!("Ollama chat failed, trying LM Studio: {}", e);
-                        self.lmstudio_client.chat(request).await
-                    }
-                }
-            },
-            "lmstudio" | "lm studio" => {
-                match self.lmstudio_client.chat(request.clone()).await {
-                    Ok(response) => Ok(response),
-                    Err(e) => {
-                        tracing::warn!("LM Studio chat failed, trying Ollama: {}", e);
-                        self.ollama_client.chat(request).await
+// Find the fallback provider's name for a model, e.g. an LM Studio GGUF path
+// for an Ollama tag
+async fn equivalent_model(client: &dyn LLMClient, model: &str) -> Option<String> {
+    let models: Vec<String> = with_probe_timeout(client.list_models()).await.ok()?
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    find_equivalent(model, &models).map(str::to_string)
+}
+
+// Health checks and model listings should answer quickly; the client's own
+// timeout is sized for generation
+async fn with_probe_timeout<T>(probe: impl Future<Output = LLMResult<T>>) -> LLMResult<T> {
+    match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(result) => result,
+        Err(_) => Err(LLMError::Timeout {
+            message: format!("No response within {}s", PROBE_TIMEOUT.as_secs()),
+        }),
+    }
+}
+
+fn offline_hint(provider: &LLMProvider) -> &'static str {
+    match provider {
+        LLMProvider::Ollama => "Ollama server not running. Start with: ollama serve",
+        LLMProvider::LMStudio => "LM Studio server not running. Start LM Studio and enable server mode.",
+        LLMProvider::OpenAI => "OpenAI-compatible endpoint is unreachable or rejected the API key.",
+    }
+}
+
+// Capabilities a request needs beyond those of its kind
+fn required(base: &[Capability], has_schema: bool) -> Vec<Capability> {
+    let mut required = base.to_vec();
+    if has_schema {
+        required.push(Capability::StructuredOutput);
+    }
+    required
+}
+
+fn chat_required(base: &[Capability], request: &ChatRequest) -> Vec<Capability> {
+    let mut required = required(base, request.schema.is_some());
+    if !request.tools.is_empty() {
+        required.push(Capability::ToolCalling);
+    }
+    if request.has_images() {
+        required.push(Capability::Vision);
+    }
+    required
+}
+
+// Whether the model itself reports a capability, e.g. "vision", or None if
+// it can't be told: the model reports no capabilities at all (older Ollama,
+// LM Studio without its native API) or can't be looked up. Hosted
+// OpenAI-style endpoints don't describe their models, so for them the
+// server's capabilities stand.
+async fn model_supports(client: &dyn LLMClient, model: &str, capability: &str) -> Option<bool> {
+    if client.provider() == LLMProvider::OpenAI {
+        return Some(true);
+    }
+    match with_probe_timeout(client.get_model_info(model)).await {
+        Ok(info) => reported_capability(&info.metadata.capabilities, capability),
+        Err(e) => {
+            tracing::debug!("Could not get capabilities of {}: {}", model, e);
+            None
+        }
+    }
+}
+
+fn reported_capability(capabilities: &[String], capability: &str) -> Option<bool> {
+    (!capabilities.is_empty()).then(|| capabilities.iter().any(|c| c == capability))
+}
+
+// The server understood the request and refused it
+fn rejected_request(error: &LLMError) -> bool {
+    match error {
+        LLMError::HttpStatus { status, .. } => (400..500).contains(status),
+        LLMError::Provider { .. } => true,
+        _ => false,
+    }
+}
+
+// Wraps a token callback so we can tell whether anything was emitted yet
+fn track_tokens(on_token: TokenCallback) -> (TokenCallback, Arc<AtomicBool>) {
+    let streamed = Arc::new(AtomicBool::new(false));
+    let flag = streamed.clone();
+    let tracked: TokenCallback = Arc::new(move |token: &str| {
+        flag.store(true, Ordering::SeqCst);
+        on_token(token);
+    });
+    (tracked, streamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_only_rebuilds_changed_providers() {
+        let ollama = ProviderConfig::new("ollama", LLMProvider::Ollama, "http://localhost:11434");
+        let lmstudio = ProviderConfig::new("lmstudio", LLMProvider::LMStudio, "http://localhost:1234");
+
+        let mut router = LLMRouter::new();
+        router.apply_provider_configs(&[ollama.clone(), lmstudio.clone()]).unwrap();
+        let client_for = |router: &LLMRouter, id: &str| router.providers.get(id).unwrap().1.clone();
+        let original_ollama = client_for(&router, "ollama");
+        let original_lmstudio = client_for(&router, "lmstudio");
+
+        let moved = ProviderConfig::new("lmstudio", LLMProvider::LMStudio, "http://10.0.0.5:1234");
+        router.apply_provider_configs(&[ollama, moved]).unwrap();
+
+        assert!(Arc::ptr_eq(&original_ollama, &client_for(&router, "ollama")));
+        assert!(!Arc::ptr_eq(&original_lmstudio, &client_for(&router, "lmstudio")));
+        assert_eq!(router.list_providers()[1].base_url, "http://10.0.0.5:1234");
+    }
+
+    #[tokio::test]
+    async fn test_providers_lacking_a_capability_are_skipped() {
+        let mut router = LLMRouter::new();
+        router.apply_provider_configs(&[
+            ProviderConfig::new("ollama", LLMProvider::Ollama, "http://localhost:11434"),
+        ]).unwrap();
+        router.versions.lock().unwrap().insert("ollama".to_string(), "0.2.8".to_string());
+
+        let result: LLMResult<((), String)> = router.with_fallback(
+            "ollama",
+            "llama3.1:8b",
+            &SamplingOptions::default(),
+            &[Capability::ToolCalling],
+            None,
+            || true,
+            |_, _| async { panic!("should not dispatch to a server without tool calling") },
+        ).await;
+
+        assert!(matches!(result, Err(LLMError::Unsupported { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_sampling_on_the_requested_provider_is_an_error() {
+        let mut router = LLMRouter::new();
+        router.apply_provider_configs(&[
+            ProviderConfig::new("lmstudio", LLMProvider::LMStudio, "http://localhost:1234"),
+            ProviderConfig::new("ollama", LLMProvider::Ollama, "http://localhost:11434"),
+        ]).unwrap();
+        let sampling = SamplingOptions { num_ctx: Some(8192), ..SamplingOptions::default() };
+
+        let result: LLMResult<((), String)> = router.with_fallback(
+            "lmstudio",
+            "qwen2.5-coder-7b-instruct",
+            &sampling,
+            &[],
+            None,
+            || true,
+            |_, _| async { panic!("should not fall back to another provider") },
+        ).await;
+
+        assert!(matches!(result, Err(LLMError::Unsupported { ref message }) if message.contains("num_ctx")));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_attempts_wait_for_a_slot_on_the_fallback_provider() {
+        use crate::llm::scheduler::{Job, Priority, QueueStatus, Scheduler, SchedulerConfig};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers the fallback's model listing once
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let body = r#"{"object":"list","data":[{"id":"llama3.1:8b","object":"model"}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body,
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let mut router = LLMRouter::new();
+        router.apply_provider_configs(&[
+            ProviderConfig::new("ollama", LLMProvider::Ollama, "http://localhost:11434"),
+            ProviderConfig::new("hosted", LLMProvider::OpenAI, &base_url),
+        ]).unwrap();
+        router.set_fallback_chain(vec!["hosted".to_string()]);
+
+        let scheduler = Scheduler::new(SchedulerConfig::default());
+        let busy = scheduler.acquire(Job {
+            request_id: "other".to_string(),
+            provider_id: "hosted".to_string(),
+            crystal: "sapphire".to_string(),
+            priority: Priority::Interactive,
+        }, None).await;
+        let schedule = Schedule {
+            scheduler: scheduler.clone(),
+            request_id: "req".to_string(),
+            crystal: "amethyst".to_string(),
+            priority: Priority::Interactive,
+            on_queue: None,
+        };
+
+        let sampling = SamplingOptions::default();
+        let request = router.with_fallback(
+            "ollama",
+            "llama3.1:8b",
+            &sampling,
+            &[],
+            Some(&schedule),
+            || true,
+            |client, _| {
+                let scheduler = scheduler.clone();
+                async move {
+                    match client.provider() {
+                        LLMProvider::Ollama => Err(LLMError::Provider { message: "model failed to load".to_string() }),
+                        _ => Ok(scheduler.status()),
                     }
                 }
             },
-            _ => {
-                // Try both, prefer Ollama
-                match self.ollama_client.chat(request.clone()).await {
-                    Ok(response) => Ok(response),
-                    Err(_) => self.lmstudio_client.chat(request).await,
-                }
+        );
+        tokio::pin!(request);
+
+        // The primary failed and gave its slot back; the fallback is full
+        assert!(tokio::time::timeout(Duration::from_millis(500), &mut request).await.is_err());
+        let status = scheduler.status();
+        assert!(!status.contains_key("ollama"));
+        assert_eq!(status["hosted"], QueueStatus { running: 1, waiting: 1 });
+
+        drop(busy);
+        let (status, provider_id) = request.await.unwrap();
+        assert_eq!(provider_id, "hosted");
+        assert_eq!(status["hosted"], QueueStatus { running: 1, waiting: 0 });
+    }
+
+    #[test]
+    fn test_missing_capabilities_are_unknown_rather_than_unsupported() {
+        let vlm = vec!["completion".to_string(), "vision".to_string()];
+        let llm = vec!["completion".to_string()];
+        assert_eq!(reported_capability(&vlm, "vision"), Some(true));
+        assert_eq!(reported_capability(&llm, "vision"), Some(false));
+        assert_eq!(reported_capability(&[], "vision"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_providers_are_probed_concurrently() {
+        // Accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = vec![];
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
             }
+        });
+
+        let mut router = LLMRouter::new();
+        router.apply_provider_configs(&[
+            ProviderConfig::new("ollama", LLMProvider::Ollama, &base_url),
+            ProviderConfig::new("lmstudio", LLMProvider::LMStudio, &base_url),
+        ]).unwrap();
+
+        let started = Instant::now();
+        let status = router.detect_servers().await.unwrap();
+
+        assert!(started.elapsed() < PROBE_TIMEOUT * 2);
+        for server in status.values() {
+            assert!(!server.connected);
+            assert!(server.error.as_deref().unwrap().contains("No response"));
         }
     }
 }
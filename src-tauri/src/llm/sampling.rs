@@ -0,0 +1,157 @@
+use super::error::{LLMError, LLMResult};
+use super::types::LLMProvider;
+use serde::{Deserialize, Serialize};
+
+/// Sampling parameters for a request. Unset parameters are left out of the
+/// request so the provider's (or model's) own defaults apply.
+///
+/// Field names follow the OpenAI API, so the struct serializes as-is into
+/// OpenAI-compatible requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Context window to load the model with (Ollama's `num_ctx`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+}
+
+// OpenAI rejects requests with more stop sequences than this
+const OPENAI_MAX_STOP_SEQUENCES: usize = 4;
+
+/// Parameters each provider accepts per request. LM Studio takes the llama.cpp
+/// samplers on its OpenAI-compatible endpoints, but sets the context size
+/// when a model is loaded.
+pub fn supported_params(provider: LLMProvider) -> &'static [&'static str] {
+    match provider {
+        LLMProvider::Ollama => &[
+            "temperature", "max_tokens", "top_p", "top_k", "min_p", "stop", "seed",
+            "repeat_penalty", "frequency_penalty", "presence_penalty", "num_ctx",
+        ],
+        LLMProvider::LMStudio => &[
+            "temperature", "max_tokens", "top_p", "top_k", "stop", "seed",
+            "repeat_penalty", "frequency_penalty", "presence_penalty",
+        ],
+        LLMProvider::OpenAI => &[
+            "temperature", "max_tokens", "top_p", "stop", "seed", "frequency_penalty", "presence_penalty",
+        ],
+    }
+}
+
+impl SamplingOptions {
+    fn set_params(&self) -> Vec<&'static str> {
+        [
+            ("temperature", self.temperature.is_some()),
+            ("max_tokens", self.max_tokens.is_some()),
+            ("top_p", self.top_p.is_some()),
+            ("top_k", self.top_k.is_some()),
+            ("min_p", self.min_p.is_some()),
+            ("stop", !self.stop.is_empty()),
+            ("seed", self.seed.is_some()),
+            ("repeat_penalty", self.repeat_penalty.is_some()),
+            ("frequency_penalty", self.frequency_penalty.is_some()),
+            ("presence_penalty", self.presence_penalty.is_some()),
+            ("num_ctx", self.num_ctx.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    /// Rejects values outside the ranges providers accept.
+    pub fn validate(&self) -> LLMResult<()> {
+        let in_range = |name: &str, value: Option<f32>, min: f32, max: f32| match value {
+            Some(v) if !(min..=max).contains(&v) => {
+                Err(LLMError::validation(format!("{} must be between {} and {}", name, min, max)))
+            }
+            _ => Ok(()),
+        };
+
+        in_range("temperature", self.temperature, 0.0, 2.0)?;
+        in_range("top_p", self.top_p, 0.0, 1.0)?;
+        in_range("min_p", self.min_p, 0.0, 1.0)?;
+        in_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+        in_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+
+        if self.max_tokens.is_some_and(|n| n <= 0) {
+            return Err(LLMError::validation("max_tokens must be positive"));
+        }
+        if self.top_k == Some(0) {
+            return Err(LLMError::validation("top_k must be positive"));
+        }
+        if self.num_ctx == Some(0) {
+            return Err(LLMError::validation("num_ctx must be positive"));
+        }
+        if self.repeat_penalty.is_some_and(|p| p <= 0.0) {
+            return Err(LLMError::validation("repeat_penalty must be positive"));
+        }
+        if self.stop.iter().any(String::is_empty) {
+            return Err(LLMError::validation("Stop sequences can't be empty"));
+        }
+        Ok(())
+    }
+
+    /// Errors with `Unsupported` if a parameter is set that `provider`
+    /// can't honour, rather than silently dropping it.
+    pub fn check_supported(&self, provider: LLMProvider) -> LLMResult<()> {
+        let supported = supported_params(provider);
+        let unsupported: Vec<&str> = self.set_params()
+            .into_iter()
+            .filter(|name| !supported.contains(name))
+            .collect();
+
+        if !unsupported.is_empty() {
+            return Err(LLMError::Unsupported {
+                message: format!("{} does not support {}", provider, unsupported.join(", ")),
+            });
+        }
+
+        if provider == LLMProvider::OpenAI && self.stop.len() > OPENAI_MAX_STOP_SEQUENCES {
+            return Err(LLMError::Unsupported {
+                message: format!("OpenAI accepts at most {} stop sequences", OPENAI_MAX_STOP_SEQUENCES),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unset_params_are_omitted() {
+        let options = SamplingOptions { temperature: Some(0.2), seed: Some(7), ..SamplingOptions::default() };
+        assert_eq!(serde_json::to_value(&options).unwrap(), serde_json::json!({ "temperature": 0.2f32, "seed": 7 }));
+    }
+
+    #[test]
+    fn test_check_supported_per_provider() {
+        let options = SamplingOptions { top_k: Some(40), num_ctx: Some(8192), ..SamplingOptions::default() };
+        assert!(options.check_supported(LLMProvider::Ollama).is_ok());
+        assert!(matches!(options.check_supported(LLMProvider::LMStudio), Err(LLMError::Unsupported { message }) if message.contains("num_ctx")));
+        assert!(matches!(options.check_supported(LLMProvider::OpenAI), Err(LLMError::Unsupported { message }) if message.contains("top_k, num_ctx")));
+
+        let invalid = SamplingOptions { top_p: Some(1.5), ..SamplingOptions::default() };
+        assert!(matches!(invalid.validate(), Err(LLMError::Validation { .. })));
+    }
+}
@@ -0,0 +1,353 @@
+use super::stream::{QueueCallback, QueueEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Interactive jobs (the user is watching the output) always start before
+/// background ones queued for the same provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    #[default]
+    Interactive,
+    Background,
+}
+
+/// How many generations each provider runs at once. Local servers mostly
+/// process one request at a time anyway, so by default extra jobs wait here,
+/// where they can be reordered, rather than in the server's own queue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub default_concurrency: usize,
+    /// Overrides by provider id
+    pub provider_concurrency: HashMap<String, usize>,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self { default_concurrency: 1, provider_concurrency: HashMap::new() }
+    }
+}
+
+impl SchedulerConfig {
+    fn limit(&self, provider_id: &str) -> usize {
+        self.provider_concurrency.get(provider_id).copied().unwrap_or(self.default_concurrency)
+    }
+}
+
+/// A generation waiting for, or holding, a slot on a provider.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub request_id: String,
+    pub provider_id: String,
+    /// Jobs are shared fairly between crystals, so one crystal queueing a
+    /// batch doesn't hold up the others
+    pub crystal: String,
+    pub priority: Priority,
+}
+
+/// Where a request stands in line. It travels with the request, so every
+/// provider the request is sent to, fallbacks included, gives it a slot.
+#[derive(Clone)]
+pub struct Schedule {
+    pub scheduler: Arc<Scheduler>,
+    pub request_id: String,
+    pub crystal: String,
+    pub priority: Priority,
+    pub on_queue: Option<QueueCallback>,
+}
+
+impl Schedule {
+    /// Waits for a slot on `provider_id`.
+    pub async fn acquire(&self, provider_id: &str) -> Permit {
+        let job = Job {
+            request_id: self.request_id.clone(),
+            provider_id: provider_id.to_string(),
+            crystal: self.crystal.clone(),
+            priority: self.priority,
+        };
+        self.scheduler.acquire(job, self.on_queue.clone()).await
+    }
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule")
+            .field("request_id", &self.request_id)
+            .field("crystal", &self.crystal)
+            .field("priority", &self.priority)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Jobs on one provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    pub running: usize,
+    pub waiting: usize,
+}
+
+/// Queues generation jobs per provider and starts them within the
+/// provider's concurrency limit.
+///
+/// The next job is the one with the highest priority, then from the crystal
+/// that was served longest ago, then the oldest. Dropping a queued job's
+/// future takes it out of the queue.
+pub struct Scheduler {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    config: SchedulerConfig,
+    providers: HashMap<String, ProviderQueue>,
+    next_seq: u64,
+}
+
+#[derive(Default)]
+struct ProviderQueue {
+    running: usize,
+    waiting: Vec<Waiter>,
+    // When each crystal last had a job started, as a grant count. Dropped
+    // with the queue once the provider is idle.
+    last_served: HashMap<String, u64>,
+    grants: u64,
+}
+
+struct Waiter {
+    seq: u64,
+    job: Job,
+    on_queue: Option<QueueCallback>,
+    start: oneshot::Sender<()>,
+}
+
+// Queue events to send once the state lock is released
+type Notifications = Vec<(QueueCallback, QueueEvent)>;
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Arc<Self> {
+        Arc::new(Self { state: Mutex::new(State { config, ..State::default() }) })
+    }
+
+    /// Raising a limit starts queued jobs right away; lowering one lets
+    /// running jobs finish.
+    pub fn set_config(&self, config: SchedulerConfig) {
+        let mut notifications = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            state.config = config;
+            let providers: Vec<String> = state.providers.keys().cloned().collect();
+            for provider_id in providers {
+                state.start_ready(&provider_id, &mut notifications);
+            }
+        }
+        notify(notifications);
+    }
+
+    /// Waits until `job` may run. The returned permit holds its slot until
+    /// dropped.
+    pub async fn acquire(self: &Arc<Self>, job: Job, on_queue: Option<QueueCallback>) -> Permit {
+        let provider_id = job.provider_id.clone();
+        let mut notifications = vec![];
+        let (seq, started) = {
+            let mut state = self.state.lock().unwrap();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let (start, started) = oneshot::channel();
+            state.providers.entry(provider_id.clone()).or_default()
+                .waiting.push(Waiter { seq, job, on_queue, start });
+            state.start_ready(&provider_id, &mut notifications);
+            (seq, started)
+        };
+        notify(notifications);
+
+        let mut waiting = Waiting { scheduler: self, provider_id: &provider_id, seq, done: false };
+        // The sender is only dropped along with the scheduler
+        let _ = started.await;
+        waiting.done = true;
+        drop(waiting);
+        Permit { scheduler: self.clone(), provider_id }
+    }
+
+    /// Jobs running and waiting, by provider id. Idle providers are left out.
+    pub fn status(&self) -> HashMap<String, QueueStatus> {
+        let state = self.state.lock().unwrap();
+        state.providers.iter()
+            .map(|(id, queue)| (id.clone(), QueueStatus { running: queue.running, waiting: queue.waiting.len() }))
+            .collect()
+    }
+
+    fn release(&self, provider_id: &str) {
+        let mut notifications = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(queue) = state.providers.get_mut(provider_id) {
+                queue.running -= 1;
+            }
+            state.start_ready(provider_id, &mut notifications);
+        }
+        notify(notifications);
+    }
+
+    // Called when an acquire future is dropped. A job still waiting leaves
+    // the queue; one started in the meantime gives its slot back.
+    fn abandon(&self, provider_id: &str, seq: u64) {
+        let mut notifications = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            let Some(queue) = state.providers.get_mut(provider_id) else { return };
+            match queue.waiting.iter().position(|w| w.seq == seq) {
+                Some(index) => {
+                    queue.waiting.remove(index);
+                }
+                None => queue.running -= 1,
+            }
+            state.start_ready(provider_id, &mut notifications);
+        }
+        notify(notifications);
+    }
+}
+
+impl State {
+    // Starts queued jobs while the provider has free slots, then reports the
+    // new positions of those still waiting
+    fn start_ready(&mut self, provider_id: &str, notifications: &mut Notifications) {
+        let limit = self.config.limit(provider_id);
+        let Some(queue) = self.providers.get_mut(provider_id) else { return };
+
+        queue.order();
+        while queue.running < limit && !queue.waiting.is_empty() {
+            let waiter = queue.waiting.remove(0);
+            queue.grants += 1;
+            queue.last_served.insert(waiter.job.crystal.clone(), queue.grants);
+            queue.running += 1;
+            if let Some(on_queue) = &waiter.on_queue {
+                notifications.push((on_queue.clone(), waiter.job.event(0)));
+            }
+            // The receiver is gone only if the job was dropped, and then
+            // abandon() frees the slot
+            let _ = waiter.start.send(());
+            queue.order();
+        }
+
+        for (index, waiter) in queue.waiting.iter().enumerate() {
+            if let Some(on_queue) = &waiter.on_queue {
+                notifications.push((on_queue.clone(), waiter.job.event(index + 1)));
+            }
+        }
+
+        if queue.running == 0 && queue.waiting.is_empty() {
+            self.providers.remove(provider_id);
+        }
+    }
+}
+
+impl ProviderQueue {
+    fn order(&mut self) {
+        let last_served = &self.last_served;
+        self.waiting.sort_by_key(|w| {
+            (w.job.priority, last_served.get(&w.job.crystal).copied().unwrap_or(0), w.seq)
+        });
+    }
+}
+
+impl Job {
+    fn event(&self, position: usize) -> QueueEvent {
+        QueueEvent { request_id: self.request_id.clone(), provider_id: self.provider_id.clone(), position }
+    }
+}
+
+fn notify(notifications: Notifications) {
+    for (on_queue, event) in notifications {
+        on_queue(&event);
+    }
+}
+
+// Takes a job out of the queue if acquire() is dropped before it starts
+struct Waiting<'a> {
+    scheduler: &'a Scheduler,
+    provider_id: &'a str,
+    seq: u64,
+    done: bool,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.scheduler.abandon(self.provider_id, self.seq);
+        }
+    }
+}
+
+/// A running job's slot on its provider, freed on drop.
+pub struct Permit {
+    scheduler: Arc<Scheduler>,
+    provider_id: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.provider_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(request_id: &str, crystal: &str, priority: Priority) -> Job {
+        Job {
+            request_id: request_id.to_string(),
+            provider_id: "ollama".to_string(),
+            crystal: crystal.to_string(),
+            priority,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_start_by_priority_then_fairly_across_crystals() {
+        let scheduler = Scheduler::new(SchedulerConfig::default());
+        let started = Arc::new(Mutex::new(vec![]));
+        let running = scheduler.acquire(job("first", "amethyst", Priority::Interactive), None).await;
+
+        let mut handles = vec![];
+        for (request_id, crystal, priority) in [
+            ("batch", "amethyst", Priority::Background),
+            ("a1", "amethyst", Priority::Interactive),
+            ("a2", "amethyst", Priority::Interactive),
+            ("s1", "sapphire", Priority::Interactive),
+        ] {
+            let scheduler = scheduler.clone();
+            let started = started.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(job(request_id, crystal, priority), None).await;
+                started.lock().unwrap().push(request_id);
+            }));
+            tokio::task::yield_now().await;
+        }
+
+        // A crystal not served yet queues ahead of amethyst's jobs, and a
+        // dropped job leaves the queue without taking a slot
+        let positions = Arc::new(Mutex::new(vec![]));
+        let on_queue: QueueCallback = {
+            let positions = positions.clone();
+            Arc::new(move |event: &QueueEvent| positions.lock().unwrap().push(event.position))
+        };
+        let dropped = scheduler.acquire(job("dropped", "ruby", Priority::Interactive), Some(on_queue));
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(10), dropped).await.is_err());
+        assert_eq!(positions.lock().unwrap().last(), Some(&2));
+        assert_eq!(scheduler.status()["ollama"], QueueStatus { running: 1, waiting: 4 });
+
+        drop(running);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        // The background job runs last
+        assert_eq!(*started.lock().unwrap(), vec!["s1", "a1", "a2", "batch"]);
+        // An idle provider's queue, and what it remembers about crystals, is dropped
+        assert!(scheduler.status().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use super::error::LLMResult;
+use super::types::{PullProgress, ToolCall};
+use futures::StreamExt;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Tauri event names emitted while a streaming generation is running
+pub const TOKEN_EVENT: &str = "llm-token";
+pub const COMPLETE_EVENT: &str = "llm-complete";
+pub const PULL_PROGRESS_EVENT: &str = "llm-pull-progress";
+pub const TOOL_CALL_EVENT: &str = "llm-tool-call";
+pub const QUEUE_EVENT: &str = "llm-queue";
+
+/// Replaces the clients' 120s request timeout for streamed generations,
+/// which keep going for as long as the model writes.
+pub const STREAM_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Callback invoked with every token (or token fragment) as it arrives.
+pub type TokenCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenEvent {
+    pub request_id: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamCompleteEvent<T> {
+    pub request_id: String,
+    pub response: T,
+}
+
+/// Callback invoked with each progress update of a model download.
+pub type PullProgressCallback = Arc<dyn Fn(&PullProgress) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullProgressEvent {
+    pub request_id: String,
+    #[serde(flatten)]
+    pub progress: PullProgress,
+}
+
+/// Callback invoked after each tool call with the result passed back to the model.
+pub type ToolCallback = Arc<dyn Fn(&ToolCall, &str) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallEvent {
+    pub request_id: String,
+    pub call: ToolCall,
+    pub result: String,
+}
+
+/// Callback invoked whenever a scheduled job's queue position changes.
+pub type QueueCallback = Arc<dyn Fn(&QueueEvent) + Send + Sync>;
+
+/// Where a job stands in its provider's queue.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEvent {
+    pub request_id: String,
+    pub provider_id: String,
+    /// 1 for the next job to start; 0 once the job is running
+    pub position: usize,
+}
+
+/// Server-sent event payloads we care about from OpenAI-style endpoints.
+#[derive(Debug, PartialEq)]
+pub enum SseData<'a> {
+    Data(&'a str),
+    Done,
+}
+
+/// Parses a single SSE line, ignoring comments, blank keep-alives and
+/// non-`data` fields.
+pub fn parse_sse_line(line: &str) -> Option<SseData<'_>> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data.is_empty() {
+        None
+    } else if data == "[DONE]" {
+        Some(SseData::Done)
+    } else {
+        Some(SseData::Data(data))
+    }
+}
+
+/// Splits a byte stream into newline-terminated lines, buffering partial
+/// lines across chunk boundaries.
+#[derive(Default)]
+pub struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+        let mut lines = vec![];
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&self.buf).trim().to_string();
+        self.buf.clear();
+        if rest.is_empty() {
+            None
+        } else {
+            Some(rest)
+        }
+    }
+}
+
+/// Feeds each line of a streaming HTTP response to `on_line` until the body
+/// ends or the handler returns `Ok(false)`.
+pub async fn for_each_line<F>(response: reqwest::Response, mut on_line: F) -> LLMResult<()>
+where
+    F: FnMut(&str) -> LLMResult<bool>,
+{
+    let mut body = response.bytes_stream();
+    let mut buffer = LineBuffer::default();
+
+    while let Some(chunk) = body.next().await {
+        for line in buffer.push(&chunk?) {
+            if !on_line(&line)? {
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(line) = buffer.finish() {
+        on_line(&line)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_joins_split_chunks() {
+        let mut buffer = LineBuffer::default();
+        assert!(buffer.push(b"{\"response\":\"he").is_empty());
+        assert_eq!(buffer.push(b"llo\"}\n{\"done\"").len(), 1);
+        assert_eq!(buffer.finish(), Some("{\"done\"".to_string()));
+    }
+
+    #[test]
+    fn test_sse_parsing() {
+        assert_eq!(parse_sse_line("data: {\"a\":1}"), Some(SseData::Data("{\"a\":1}")));
+        assert_eq!(parse_sse_line("data: [DONE]"), Some(SseData::Done));
+        assert_eq!(parse_sse_line(": keep-alive"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use super::error::{LLMError, LLMResult};
+use super::router::LLMRouter;
+use super::stream::TokenCallback;
+use super::types::*;
+use serde::Serialize;
+use serde_json::Value;
+
+/// OpenAI-style `response_format` constraining output to a JSON schema.
+#[derive(Debug, Serialize)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    json_schema: JsonSchemaFormat,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonSchemaFormat {
+    name: &'static str,
+    schema: Value,
+}
+
+impl ResponseFormat {
+    pub fn json_schema(schema: Value) -> Self {
+        Self {
+            kind: "json_schema",
+            json_schema: JsonSchemaFormat { name: "response", schema },
+        }
+    }
+}
+
+/// Like `generate_with_fallback` for a request carrying a schema: the output
+/// is checked against the schema, and on a mismatch the model is shown the
+/// error and asked once more. The retry isn't streamed, so when streaming the
+/// completed response is the one to use.
+pub async fn generate(
+    router: &LLMRouter,
+    provider: &str,
+    request: GenerateRequest,
+    on_token: Option<TokenCallback>,
+) -> LLMResult<GenerateResponse> {
+    let validator = compile(request.schema.as_ref())?;
+    let first = match on_token {
+        Some(on_token) => router.generate_stream_with_fallback(provider, request.clone(), on_token).await?,
+        None => router.generate_with_fallback(provider, request.clone()).await?,
+    };
+    let response = validate_or_retry(router, &validator, request.into(), first.into()).await?;
+    Ok(response.into())
+}
+
+/// Chat counterpart of [`generate`].
+pub async fn chat(
+    router: &LLMRouter,
+    provider: &str,
+    request: ChatRequest,
+    on_token: Option<TokenCallback>,
+) -> LLMResult<ChatResponse> {
+    let validator = compile(request.schema.as_ref())?;
+    let first = match on_token {
+        Some(on_token) => router.chat_stream_with_fallback(provider, request.clone(), on_token).await?,
+        None => router.chat_with_fallback(provider, request.clone()).await?,
+    };
+    validate_or_retry(router, &validator, request, first).await
+}
+
+fn compile(schema: Option<&Value>) -> LLMResult<jsonschema::Validator> {
+    let schema = schema.ok_or_else(|| LLMError::validation("Structured output needs a schema"))?;
+    jsonschema::validator_for(schema)
+        .map_err(|e| LLMError::validation(format!("Invalid JSON schema: {}", e)))
+}
+
+async fn validate_or_retry(
+    router: &LLMRouter,
+    validator: &jsonschema::Validator,
+    request: ChatRequest,
+    mut response: ChatResponse,
+) -> LLMResult<ChatResponse> {
+    let error = match check(validator, &response.message.content) {
+        Ok(json) => {
+            response.message.content = json;
+            return Ok(response);
+        }
+        Err(error) => error,
+    };
+    tracing::warn!("Structured output did not match the schema, retrying: {}", error);
+
+    let mut messages = request.messages.clone();
+    messages.push(Message::assistant(&response.message.content));
+    messages.push(Message::user(&format!(
+        "That response is invalid: {}. Reply again with only the corrected JSON.",
+        error
+    )));
+    // Ask the provider and model that produced the invalid output
+    let retry_request = ChatRequest { model: response.model.clone(), messages, stream: false, ..request };
+    let mut retry = router.chat_with_fallback(&response.provider_id, retry_request).await?;
+
+    retry.usage = response.usage + retry.usage;
+    retry.generation_time_ms += response.generation_time_ms;
+    match check(validator, &retry.message.content) {
+        Ok(json) => {
+            retry.message.content = json;
+            Ok(retry)
+        }
+        Err(message) => Err(LLMError::SchemaMismatch { message, output: retry.message.content }),
+    }
+}
+
+// The output as compact JSON if it parses and matches, otherwise a
+// description of what's wrong that the model can act on
+fn check(validator: &jsonschema::Validator, output: &str) -> Result<String, String> {
+    let value: Value = serde_json::from_str(strip_code_fence(output))
+        .map_err(|e| format!("not valid JSON ({})", e))?;
+
+    let errors: Vec<String> = validator.iter_errors(&value)
+        .map(|e| match e.instance_path.to_string() {
+            path if path.is_empty() => e.to_string(),
+            path => format!("{} at {}", e, path),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    Ok(value.to_string())
+}
+
+// Servers without constrained decoding often wrap JSON in ```json fences
+fn strip_code_fence(output: &str) -> &str {
+    let trimmed = output.trim();
+    trimmed.strip_prefix("```")
+        .and_then(|rest| rest.split_once('\n'))
+        .and_then(|(_, body)| body.trim_end().strip_suffix("```"))
+        .unwrap_or(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn component_schema() -> jsonschema::Validator {
+        compile(Some(&json!({
+            "type": "object",
+            "properties": {
+                "code": { "type": "string" },
+                "explanation": { "type": "string" }
+            },
+            "required": ["code", "explanation"]
+        }))).unwrap()
+    }
+
+    #[test]
+    fn test_check_accepts_fenced_json_and_reports_mismatches() {
+        let validator = component_schema();
+
+        let fenced = "```json\n{\"code\": \"<Button />\", \"explanation\": \"A button\"}\n```";
+        assert_eq!(check(&validator, fenced).unwrap(), r#"{"code":"<Button />","explanation":"A button"}"#);
+
+        let missing = check(&validator, r#"{"code": "<Button />"}"#).unwrap_err();
+        assert!(missing.contains("explanation"));
+
+        let wrong_type = check(&validator, r#"{"code": 1, "explanation": ""}"#).unwrap_err();
+        assert!(wrong_type.contains("/code"));
+
+        assert!(check(&validator, "Here is your component").unwrap_err().starts_with("not valid JSON"));
+    }
+
+    #[test]
+    fn test_response_format_wire_shape() {
+        let format = ResponseFormat::json_schema(json!({ "type": "object" }));
+        assert_eq!(serde_json::to_value(&format).unwrap(), json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "schema": { "type": "object" } }
+        }));
+    }
+}
@@ -0,0 +1,80 @@
+pub mod registry;
+
+pub use registry::{Tool, ToolRegistry};
+
+use super::error::{LLMError, LLMResult};
+use super::router::LLMRouter;
+use super::stream::{TokenCallback, ToolCallback};
+use super::types::*;
+use serde::Serialize;
+
+// Rounds of tool calls allowed before giving up on a final answer
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// A tool in the `{"type": "function", "function": {...}}` shape that both
+/// Ollama and OpenAI-style endpoints take.
+#[derive(Debug, Serialize)]
+pub struct FunctionTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: ToolDefinition,
+}
+
+impl From<ToolDefinition> for FunctionTool {
+    fn from(function: ToolDefinition) -> Self {
+        Self { kind: "function", function }
+    }
+}
+
+/// Like `chat_with_fallback` for a request offering tools: whenever the model
+/// answers with tool calls they're run through `registry` and the results
+/// sent back, until it replies without calling any. Only the final reply's
+/// tokens are meaningful to stream, but every round's are passed to
+/// `on_token` as they arrive.
+pub async fn chat_with_tools(
+    router: &LLMRouter,
+    provider: &str,
+    mut request: ChatRequest,
+    registry: &ToolRegistry,
+    on_token: Option<TokenCallback>,
+    on_tool: Option<ToolCallback>,
+) -> LLMResult<ChatResponse> {
+    let mut provider = provider.to_string();
+    let mut usage = TokenUsage::default();
+    let mut generation_time_ms = 0;
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let mut response = match &on_token {
+            Some(on_token) => router.chat_stream_with_fallback(&provider, request.clone(), on_token.clone()).await?,
+            None => router.chat_with_fallback(&provider, request.clone()).await?,
+        };
+        usage = usage + response.usage;
+        generation_time_ms += response.generation_time_ms;
+
+        if response.message.tool_calls.is_empty() {
+            response.usage = usage;
+            response.generation_time_ms = generation_time_ms;
+            return Ok(response);
+        }
+
+        // Keep the conversation on the provider and model that started it,
+        // since tool call ids only mean something to them
+        provider = response.provider_id.clone();
+        request.model = response.model.clone();
+
+        let calls = response.message.tool_calls.clone();
+        request.messages.push(response.message);
+        for call in &calls {
+            tracing::debug!("Model called tool {} with {}", call.name, call.arguments);
+            let result = registry.call(call).await;
+            if let Some(on_tool) = &on_tool {
+                on_tool(call, &result);
+            }
+            request.messages.push(Message::tool_result(call, &result));
+        }
+    }
+
+    Err(LLMError::Provider {
+        message: format!("Model kept calling tools after {} rounds", MAX_TOOL_ROUNDS),
+    })
+}
@@ -0,0 +1,127 @@
+use crate::llm::error::{LLMError, LLMResult};
+use crate::llm::types::{ToolCall, ToolDefinition};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+// A tool that hangs shouldn't stall the conversation
+const TOOL_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Results go back into the context window, so keep them bounded
+const MAX_RESULT_BYTES: usize = 32 * 1024;
+
+/// A function the model can call. Tools run in-process, so implementations
+/// decide what they expose; none of the built-in ones write anything.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    async fn call(&self, arguments: Value) -> anyhow::Result<String>;
+}
+
+/// The tools the backend will execute on a model's behalf. Calls for
+/// anything not registered here are refused.
+#[derive(Default, Clone)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.insert(tool.definition().name, Arc::new(tool));
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.values().map(|tool| tool.definition()).collect()
+    }
+
+    /// Definitions for the named tools, failing on any that aren't registered.
+    pub fn select(&self, names: &[String]) -> LLMResult<Vec<ToolDefinition>> {
+        names.iter()
+            .map(|name| {
+                self.tools.get(name)
+                    .map(|tool| tool.definition())
+                    .ok_or_else(|| LLMError::validation(format!("Unknown tool: {}", name)))
+            })
+            .collect()
+    }
+
+    /// Runs a call and returns what to send back to the model. Failures are
+    /// reported as text too, so the model can correct itself.
+    pub async fn call(&self, call: &ToolCall) -> String {
+        let Some(tool) = self.tools.get(&call.name) else {
+            return format!("Error: unknown tool {}", call.name);
+        };
+
+        let result = match tokio::time::timeout(TOOL_TIMEOUT, tool.call(call.arguments.clone())).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => format!("Error: {}", e),
+            Err(_) => format!("Error: {} timed out after {}s", call.name, TOOL_TIMEOUT.as_secs()),
+        };
+        truncate(result)
+    }
+}
+
+fn truncate(mut text: String) -> String {
+    if text.len() > MAX_RESULT_BYTES {
+        let mut end = MAX_RESULT_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("\n[truncated]");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "echo".to_string(),
+                description: "Echoes its text argument".to_string(),
+                parameters: json!({ "type": "object", "properties": { "text": { "type": "string" } } }),
+            }
+        }
+
+        async fn call(&self, arguments: Value) -> anyhow::Result<String> {
+            match arguments["text"].as_str() {
+                Some(text) => Ok(text.repeat(2)),
+                None => anyhow::bail!("text is required"),
+            }
+        }
+    }
+
+    fn call(name: &str, arguments: Value) -> ToolCall {
+        ToolCall { id: "call_0".to_string(), name: name.to_string(), arguments }
+    }
+
+    #[tokio::test]
+    async fn test_registry_only_runs_registered_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Echo);
+
+        assert_eq!(registry.call(&call("echo", json!({ "text": "ab" }))).await, "abab");
+        assert_eq!(registry.call(&call("echo", json!({}))).await, "Error: text is required");
+        assert_eq!(registry.call(&call("shell", json!({}))).await, "Error: unknown tool shell");
+
+        let huge = registry.call(&call("echo", json!({ "text": "x".repeat(MAX_RESULT_BYTES) }))).await;
+        assert!(huge.ends_with("[truncated]"));
+
+        assert!(registry.select(&["echo".to_string()]).is_ok());
+        assert!(matches!(registry.select(&["shell".to_string()]), Err(LLMError::Validation { .. })));
+    }
+}
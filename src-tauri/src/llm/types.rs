@@ -1,7 +1,15 @@
+use super::capabilities::ServerCapabilities;
+use super::error::{LLMError, LLMResult};
+use super::resilience::CircuitState;
+use super::sampling::SamplingOptions;
+use super::scheduler::Schedule;
+use super::stream::{PullProgressCallback, TokenCallback};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LLMProvider {
     Ollama,
     LMStudio,
@@ -32,16 +40,39 @@ pub struct ModelInfo {
     pub name: String,
     pub size: Option<u64>,
     pub provider: LLMProvider,
+    // Registry id of the provider instance serving this model (set by the router)
+    #[serde(default)]
+    pub provider_id: String,
     pub status: ModelStatus,
     pub performance: Option<PerformanceMetrics>,
     pub context_length: Option<u32>,
     pub quantization: Option<String>,
+    #[serde(flatten)]
+    pub metadata: ModelMetadata,
+}
+
+/// Extra details some providers report about a model. Everything is
+/// optional since most OpenAI-style endpoints only return an id.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    pub digest: Option<String>,
+    pub modified_at: Option<String>,
+    pub family: Option<String>,
+    pub parameter_count: Option<u64>,
+    pub template: Option<String>,
+    // e.g. "completion", "tools", "vision", "embedding"
+    #[serde(default)]
+    pub capabilities: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
     pub tokens_per_second: f64,
+    // Milliseconds
     pub time_to_first_token: Option<f64>,
+    #[serde(default)]
+    pub prompt_tokens_per_second: Option<f64>,
+    // Peak bytes used by the server process
     pub memory_usage: Option<u64>,
     pub last_updated: String, // Using String for simplicity, convert from DateTime
 }
@@ -50,25 +81,177 @@ pub struct PerformanceMetrics {
 pub struct GenerateRequest {
     pub model: String,
     pub prompt: String,
-    pub temperature: Option<f32>,
-    pub max_tokens: Option<i32>,
-    pub top_p: Option<f32>,
+    #[serde(flatten)]
+    pub sampling: SamplingOptions,
+    /// JSON Schema the output must match (structured output)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
     pub stream: bool,
+    /// Queues each attempt for a slot on the provider it goes to
+    #[serde(skip)]
+    pub schedule: Option<Schedule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenerateResponse {
     pub text: String,
     pub model: String,
+    // Registry id of the provider that served the request (set by the router)
+    #[serde(default)]
+    pub provider_id: String,
     pub tokens_generated: u32,
     pub generation_time_ms: u64,
     pub tokens_per_second: f64,
+    #[serde(default)]
+    pub timings: GenerationTimings,
+    #[serde(default)]
+    pub usage: TokenUsage,
+    #[serde(default)]
+    pub finish_reason: FinishReason,
+    /// Served from the response cache rather than the model
+    #[serde(default)]
+    pub cache_hit: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where the time of a generation went, in milliseconds. Providers that
+/// report their own timings (Ollama) fill in the breakdown; for the rest only
+/// wall-clock figures are known.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerationTimings {
+    pub total_ms: Option<f64>,
+    pub load_ms: Option<f64>,
+    pub prompt_eval_ms: Option<f64>,
+    pub prompt_eval_count: Option<u32>,
+    pub eval_ms: Option<f64>,
+    /// Streaming only, measured from sending the request
+    pub time_to_first_token_ms: Option<f64>,
+    /// Whether the durations come from the provider rather than our clock
+    pub provider_reported: bool,
+}
+
+impl GenerationTimings {
+    pub fn measured(total: Duration, time_to_first_token: Option<Duration>) -> Self {
+        Self {
+            total_ms: Some(total.as_secs_f64() * 1000.0),
+            time_to_first_token_ms: time_to_first_token.map(|d| d.as_secs_f64() * 1000.0),
+            ..Self::default()
+        }
+    }
+
+    /// Decode speed, using the provider's eval time when known. Otherwise the
+    /// wall-clock time after the first token, and failing that the total.
+    pub fn tokens_per_second(&self, tokens_generated: u32) -> f64 {
+        let after_first_token = self.total_ms
+            .zip(self.time_to_first_token_ms)
+            .map(|(total, first)| total - first);
+        let decode_ms = self.eval_ms.or(after_first_token).or(self.total_ms).unwrap_or(0.0);
+
+        if decode_ms > 0.0 {
+            tokens_generated as f64 * 1000.0 / decode_ms
+        } else {
+            0.0
+        }
+    }
+
+    pub fn prompt_tokens_per_second(&self) -> Option<f64> {
+        let (count, ms) = self.prompt_eval_count.zip(self.prompt_eval_ms)?;
+        (ms > 0.0).then(|| count as f64 * 1000.0 / ms)
+    }
+}
+
+/// Timings of one generation followed by another, e.g. a continuation. A
+/// duration only one of them reports is unknown for both together; time to
+/// first token is the first generation's.
+impl std::ops::Add for GenerationTimings {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        fn sum<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+            a.zip(b).map(|(a, b)| a + b)
+        }
+        Self {
+            total_ms: sum(self.total_ms, other.total_ms),
+            load_ms: sum(self.load_ms, other.load_ms),
+            prompt_eval_ms: sum(self.prompt_eval_ms, other.prompt_eval_ms),
+            prompt_eval_count: sum(self.prompt_eval_count, other.prompt_eval_count),
+            eval_ms: sum(self.eval_ms, other.eval_ms),
+            time_to_first_token_ms: self.time_to_first_token_ms,
+            provider_reported: self.provider_reported && other.provider_reported,
+        }
+    }
+}
+
+/// Why generation stopped, normalized across providers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// Natural end of output or a stop sequence
+    Stop,
+    /// Cut off by max_tokens or the context window
+    Length,
+    ToolCalls,
+    ContentFilter,
+    /// Not reported by the provider
+    #[default]
+    Unknown,
+}
+
+impl FinishReason {
+    /// Maps OpenAI `finish_reason` and Ollama `done_reason` values.
+    pub fn from_provider(reason: Option<&str>) -> Self {
+        match reason {
+            // Ollama reports "load" and "unload" for requests that only
+            // change what's in memory
+            Some("stop" | "eos" | "stop_sequence" | "load" | "unload") => FinishReason::Stop,
+            Some("length" | "max_tokens") => FinishReason::Length,
+            Some("tool_calls" | "function_call") => FinishReason::ToolCalls,
+            Some("content_filter") => FinishReason::ContentFilter,
+            _ => FinishReason::Unknown,
+        }
+    }
+}
+
+/// Token counts for a single request. `prompt_tokens` is 0 when the provider
+/// doesn't report it, e.g. an OpenAI-compatible stream without usage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl TokenUsage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+}
+
+// Combines the usage of several requests made for one response
+impl std::ops::Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        TokenUsage::new(self.prompt_tokens + other.prompt_tokens, self.completion_tokens + other.completion_tokens)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// Base64-encoded images attached to the message, for vision models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// Tools the assistant asked to call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// On "tool" messages, the call this is the result of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 impl Message {
@@ -76,6 +259,7 @@ impl Message {
         Self {
             role: "system".to_string(),
             content: content.to_string(),
+            ..Self::default()
         }
     }
 
@@ -83,6 +267,7 @@ impl Message {
         Self {
             role: "user".to_string(),
             content: content.to_string(),
+            ..Self::default()
         }
     }
 
@@ -90,49 +275,269 @@ impl Message {
         Self {
             role: "assistant".to_string(),
             content: content.to_string(),
+            ..Self::default()
         }
     }
+
+    pub fn tool_result(call: &ToolCall, content: &str) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: content.to_string(),
+            tool_call_id: Some(call.id.clone()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A tool the model may call. `parameters` is a JSON Schema for the
+/// arguments, as in the OpenAI and Ollama APIs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<Message>,
-    pub temperature: Option<f32>,
-    pub max_tokens: Option<i32>,
-    pub top_p: Option<f32>,
+    #[serde(flatten)]
+    pub sampling: SamplingOptions,
+    /// JSON Schema the output must match (structured output)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
     pub stream: bool,
+    /// Queues each attempt for a slot on the provider it goes to
+    #[serde(skip)]
+    pub schedule: Option<Schedule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatResponse {
     pub message: Message,
     pub model: String,
+    // Registry id of the provider that served the request (set by the router)
+    #[serde(default)]
+    pub provider_id: String,
     pub tokens_generated: u32,
     pub generation_time_ms: u64,
     pub tokens_per_second: f64,
+    #[serde(default)]
+    pub timings: GenerationTimings,
+    #[serde(default)]
+    pub usage: TokenUsage,
+    #[serde(default)]
+    pub finish_reason: FinishReason,
+    /// Served from the response cache rather than the model
+    #[serde(default)]
+    pub cache_hit: bool,
+}
+
+/// Vectors for a batch of inputs, in input order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub model: String,
+    // Registry id of the provider that served the request (set by the router)
+    #[serde(default)]
+    pub provider_id: String,
+    pub embeddings: Vec<Vec<f32>>,
+    /// Length of every vector
+    pub dimensions: usize,
+    /// Only prompt tokens are counted
+    #[serde(default)]
+    pub usage: TokenUsage,
+}
+
+impl EmbeddingResponse {
+    pub fn new(model: String, embeddings: Vec<Vec<f32>>, usage: TokenUsage) -> LLMResult<Self> {
+        let dimensions = embeddings.first().map_or(0, Vec::len);
+        if embeddings.iter().any(|e| e.len() != dimensions) {
+            return Err(LLMError::Decode { message: "Embeddings have differing dimensions".to_string() });
+        }
+        Ok(Self { model, provider_id: String::new(), embeddings, dimensions, usage })
+    }
+}
+
+/// Which models to list: ones that generate text, or embedding models.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    #[default]
+    Generation,
+    Embedding,
+}
+
+impl ChatRequest {
+    pub fn has_images(&self) -> bool {
+        self.messages.iter().any(|m| !m.images.is_empty())
+    }
+}
+
+// A prompt is a single user turn
+impl From<GenerateRequest> for ChatRequest {
+    fn from(request: GenerateRequest) -> Self {
+        Self {
+            model: request.model,
+            messages: vec![Message::user(&request.prompt)],
+            sampling: request.sampling,
+            schema: request.schema,
+            tools: vec![],
+            stream: request.stream,
+            schedule: request.schedule,
+        }
+    }
+}
+
+impl From<ChatResponse> for GenerateResponse {
+    fn from(response: ChatResponse) -> Self {
+        Self {
+            text: response.message.content,
+            model: response.model,
+            provider_id: response.provider_id,
+            tokens_generated: response.tokens_generated,
+            generation_time_ms: response.generation_time_ms,
+            tokens_per_second: response.tokens_per_second,
+            timings: response.timings,
+            usage: response.usage,
+            finish_reason: response.finish_reason,
+            cache_hit: response.cache_hit,
+        }
+    }
+}
+
+impl From<GenerateResponse> for ChatResponse {
+    fn from(response: GenerateResponse) -> Self {
+        Self {
+            message: Message::assistant(&response.text),
+            model: response.model,
+            provider_id: response.provider_id,
+            tokens_generated: response.tokens_generated,
+            generation_time_ms: response.generation_time_ms,
+            tokens_per_second: response.tokens_per_second,
+            timings: response.timings,
+            usage: response.usage,
+            finish_reason: response.finish_reason,
+            cache_hit: response.cache_hit,
+        }
+    }
 }
 
+/// One progress update while a model is being downloaded.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServerStatus {
-    pub ollama: ServerConnectionStatus,
-    pub lmstudio: ServerConnectionStatus,
+pub struct PullProgress {
+    pub model: String,
+    /// Provider's description of the current step, e.g. "pulling manifest"
+    pub status: String,
+    /// Layer currently being downloaded
+    pub digest: Option<String>,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    /// `Downloading` until the final update, then `Loaded`
+    pub model_status: ModelStatus,
+}
+
+/// A model currently resident in memory on a provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningModel {
+    pub model: String,
+    // Registry id of the provider (set by the router)
+    #[serde(default)]
+    pub provider_id: String,
+    /// Total memory used, in bytes
+    pub size: Option<u64>,
+    /// Portion of `size` held in VRAM
+    pub size_vram: Option<u64>,
+    /// When the provider will unload the model if it stays idle
+    pub expires_at: Option<String>,
+    pub context_length: Option<u32>,
 }
 
+// Connection status for every registered provider, keyed by provider id
+pub type ServerStatus = BTreeMap<String, ServerConnectionStatus>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConnectionStatus {
+    pub provider: LLMProvider,
+    pub base_url: String,
     pub connected: bool,
     pub version: Option<String>,
+    pub capabilities: ServerCapabilities,
     pub models_loaded: Vec<String>,
+    /// Round trip of the health probe, if the server answered at all
+    pub latency_ms: Option<u64>,
+    /// What went wrong while probing, e.g. a refused connection or timeout
     pub error: Option<String>,
+    /// How to bring the server up, set when it couldn't be reached
+    pub hint: Option<String>,
+    pub circuit: CircuitState,
 }
 
 // Trait for unified LLM interface
 #[async_trait::async_trait]
 pub trait LLMClient: Send + Sync {
-    async fn list_models(&self) -> anyhow::Result<Vec<ModelInfo>>;
-    async fn generate(&self, request: GenerateRequest) -> anyhow::Result<GenerateResponse>;
-    async fn chat(&self, request: ChatRequest) -> anyhow::Result<ChatResponse>;
-    async fn health_check(&self) -> anyhow::Result<bool>;
-    async fn get_model_info(&self, model_id: &str) -> anyhow::Result<ModelInfo>;
+    fn provider(&self) -> LLMProvider;
+    fn base_url(&self) -> &str;
+    async fn list_models(&self) -> LLMResult<Vec<ModelInfo>>;
+    async fn generate(&self, request: GenerateRequest) -> LLMResult<GenerateResponse>;
+    async fn chat(&self, request: ChatRequest) -> LLMResult<ChatResponse>;
+    async fn generate_stream(&self, request: GenerateRequest, on_token: TokenCallback) -> LLMResult<GenerateResponse>;
+    async fn chat_stream(&self, request: ChatRequest, on_token: TokenCallback) -> LLMResult<ChatResponse>;
+    // Err when the server can't be reached, Ok(false) when it answers with an error
+    async fn health_check(&self) -> LLMResult<bool>;
+    async fn get_model_info(&self, model_id: &str) -> LLMResult<ModelInfo>;
+
+    // Server version, if the provider exposes one
+    async fn server_version(&self) -> LLMResult<Option<String>> {
+        Ok(None)
+    }
+
+    async fn delete_model(&self, model: &str) -> LLMResult<()> {
+        let _ = model;
+        Err(self.unsupported("delete models"))
+    }
+
+    // Load a model into memory ahead of use. `keep_alive_secs` is how long it
+    // stays resident while idle; negative means until unloaded.
+    async fn load_model(&self, model: &str, keep_alive_secs: Option<i64>) -> LLMResult<()> {
+        let _ = (model, keep_alive_secs);
+        Err(self.unsupported("load models on request"))
+    }
+
+    async fn unload_model(&self, model: &str) -> LLMResult<()> {
+        let _ = model;
+        Err(self.unsupported("unload models on request"))
+    }
+
+    async fn running_models(&self) -> LLMResult<Vec<RunningModel>> {
+        Err(self.unsupported("report resident models"))
+    }
+
+    // Embed a batch of inputs, returning vectors in input order. Large
+    // inputs are split into batches by the router.
+    async fn embed(&self, model: &str, inputs: &[String]) -> LLMResult<EmbeddingResponse> {
+        let _ = (model, inputs);
+        Err(self.unsupported("compute embeddings"))
+    }
+
+    // Download a model onto the server. Only Ollama supports this.
+    async fn pull_model(&self, model: &str, on_progress: PullProgressCallback) -> LLMResult<()> {
+        let _ = (model, on_progress);
+        Err(self.unsupported("download models"))
+    }
+
+    fn unsupported(&self, operation: &str) -> LLMError {
+        LLMError::Unsupported {
+            message: format!("{} cannot {}", self.provider(), operation),
+        }
+    }
 }
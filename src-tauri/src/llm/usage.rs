@@ -0,0 +1,103 @@
+use super::types::TokenUsage;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Cumulative token counts over a number of requests.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &TokenUsage) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt_tokens as u64;
+        self.completion_tokens += usage.completion_tokens as u64;
+        self.total_tokens += usage.total_tokens as u64;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub provider_id: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    /// Everything since the app started
+    pub session: UsageTotals,
+    pub models: Vec<ModelUsage>,
+}
+
+/// Token usage of completed generations, kept in memory for the session.
+#[derive(Default)]
+pub struct UsageTracker {
+    inner: Mutex<UsageState>,
+}
+
+#[derive(Default)]
+struct UsageState {
+    session: UsageTotals,
+    // Keyed by (provider id, model)
+    models: HashMap<(String, String), UsageTotals>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, provider_id: &str, model: &str, usage: &TokenUsage) {
+        let mut state = self.inner.lock().unwrap();
+        state.session.add(usage);
+        state.models
+            .entry((provider_id.to_string(), model.to_string()))
+            .or_default()
+            .add(usage);
+    }
+
+    /// Totals for the session and per model, heaviest models first.
+    pub fn report(&self) -> UsageReport {
+        let state = self.inner.lock().unwrap();
+        let mut models: Vec<ModelUsage> = state.models.iter()
+            .map(|((provider_id, model), totals)| ModelUsage {
+                provider_id: provider_id.clone(),
+                model: model.clone(),
+                totals: *totals,
+            })
+            .collect();
+        models.sort_by_key(|m| std::cmp::Reverse(m.totals.total_tokens));
+
+        UsageReport { session: state.session, models }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totals_accumulate_per_model_and_session() {
+        let tracker = UsageTracker::new();
+        tracker.record("ollama", "llama3.1:8b", &TokenUsage::new(1200, 300));
+        tracker.record("ollama", "llama3.1:8b", &TokenUsage::new(1100, 200));
+        tracker.record("lmstudio", "qwen2.5-coder-7b", &TokenUsage::new(900, 100));
+
+        let report = tracker.report();
+        assert_eq!(report.session.requests, 3);
+        assert_eq!(report.session.prompt_tokens, 3200);
+        assert_eq!(report.session.total_tokens, 3800);
+
+        assert_eq!(report.models.len(), 2);
+        assert_eq!(report.models[0].model, "llama3.1:8b");
+        assert_eq!(report.models[0].totals.requests, 2);
+        assert_eq!(report.models[0].totals.completion_tokens, 500);
+    }
+}
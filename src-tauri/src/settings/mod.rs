@@ -0,0 +1,364 @@
+pub mod paths;
+
+use crate::llm::{CircuitBreakerConfig, LLMProvider, ProviderConfig, RetryPolicy};
+use crate::llm::scheduler::SchedulerConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+pub const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_FILE: &str = "settings.json";
+// Where an unreadable settings file is moved so saving doesn't destroy it
+const BACKUP_FILE: &str = "settings.json.bak";
+
+// Upgrades a settings document from version N to N + 1, where N is the
+// migration's position in the list plus one
+type Migration = fn(&mut Value) -> Result<()>;
+const MIGRATIONS: &[Migration] = &[];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub providers: Vec<ProviderConfig>,
+    pub default_provider: Option<String>,
+    pub default_model: Option<String>,
+    /// Embedding model used for semantic search over saved components.
+    /// Without one, search only matches keywords.
+    pub embedding_provider: Option<String>,
+    pub embedding_model: Option<String>,
+    /// Provider ids to try, in order, when the requested provider fails.
+    /// Empty means every local provider in the order listed above.
+    pub fallback_chain: Vec<String>,
+    /// Retries for connection errors, timeouts, 429 and 5xx responses
+    pub retry: RetryPolicy,
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Size limit of the on-disk response cache, in megabytes
+    pub response_cache_max_mb: u64,
+    /// Generations each provider runs at once
+    pub scheduler: SchedulerConfig,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            providers: vec![
+                ProviderConfig::new("ollama", LLMProvider::Ollama, "http://localhost:11434"),
+                ProviderConfig::new("lmstudio", LLMProvider::LMStudio, "http://localhost:1234"),
+            ],
+            default_provider: None,
+            default_model: None,
+            embedding_provider: None,
+            embedding_model: None,
+            fallback_chain: vec![],
+            retry: RetryPolicy::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            response_cache_max_mb: 256,
+            scheduler: SchedulerConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn response_cache_max_bytes(&self) -> u64 {
+        self.response_cache_max_mb * 1024 * 1024
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        for provider in &self.providers {
+            if provider.id.trim().is_empty() {
+                anyhow::bail!("Provider id must not be empty");
+            }
+            if !ids.insert(provider.id.as_str()) {
+                anyhow::bail!("Duplicate provider id: {}", provider.id);
+            }
+            reqwest::Url::parse(&provider.base_url)
+                .with_context(|| format!("Invalid base URL for provider {}: {}", provider.id, provider.base_url))?;
+        }
+
+        if let Some(default_provider) = &self.default_provider {
+            if !ids.contains(default_provider.as_str()) {
+                anyhow::bail!("Default provider {} is not configured", default_provider);
+            }
+        }
+
+        if let Some(embedding_provider) = &self.embedding_provider {
+            if !ids.contains(embedding_provider.as_str()) {
+                anyhow::bail!("Embedding provider {} is not configured", embedding_provider);
+            }
+        }
+        if self.embedding_provider.is_some() != self.embedding_model.is_some() {
+            anyhow::bail!("Embedding provider and model must be set together");
+        }
+
+        if self.retry.max_attempts == 0 {
+            anyhow::bail!("Retry policy needs at least one attempt");
+        }
+        if !(0.0..=1.0).contains(&self.retry.jitter) || self.retry.multiplier < 1.0 {
+            anyhow::bail!("Retry jitter must be between 0 and 1 and the multiplier at least 1");
+        }
+
+        if self.response_cache_max_mb == 0 {
+            anyhow::bail!("Response cache size must be at least 1 MB");
+        }
+
+        if self.scheduler.default_concurrency == 0 || self.scheduler.provider_concurrency.values().any(|&n| n == 0) {
+            anyhow::bail!("Provider concurrency must be at least 1");
+        }
+        if let Some(unknown) = self.scheduler.provider_concurrency.keys().find(|id| !ids.contains(id.as_str())) {
+            anyhow::bail!("Concurrency limit set for unknown provider {}", unknown);
+        }
+
+        if let Some(unknown) = self.fallback_chain.iter().find(|id| !ids.contains(id.as_str())) {
+            anyhow::bail!("Fallback provider {} is not configured", unknown);
+        }
+
+        Ok(())
+    }
+}
+
+/// An environment variable currently overriding a stored setting.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvOverride {
+    pub variable: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsSnapshot {
+    /// Settings as stored on disk, which is what the settings UI edits
+    pub settings: Settings,
+    pub env_overrides: Vec<EnvOverride>,
+}
+
+/// Settings persisted as JSON in the app config dir.
+///
+/// Environment variables (`OLLAMA_BASE_URL`, `LMSTUDIO_BASE_URL`,
+/// `DEFAULT_PROVIDER`, `DEFAULT_MODEL`) take precedence at runtime but are
+/// never written back to the file.
+pub struct SettingsStore {
+    path: Option<PathBuf>,
+    settings: Settings,
+}
+
+impl SettingsStore {
+    /// Loads settings from the app config dir, falling back to defaults if
+    /// the file is missing or unreadable. An unreadable file (corrupt, or
+    /// from a newer version) is kept as settings.json.bak.
+    pub fn load() -> Self {
+        Self::load_from(paths::app_config_dir().map(|dir| dir.join(SETTINGS_FILE)))
+    }
+
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let settings = match &path {
+            Some(path) => read_settings(path).unwrap_or_else(|e| {
+                tracing::error!("Failed to load settings from {}: {:#}", path.display(), e);
+                let backup = path.with_file_name(BACKUP_FILE);
+                match std::fs::rename(path, &backup) {
+                    Ok(()) => tracing::warn!("Moved unreadable settings to {}", backup.display()),
+                    Err(e) => tracing::error!("Failed to back up unreadable settings: {}", e),
+                }
+                Settings::default()
+            }),
+            None => {
+                tracing::warn!("No config directory available, settings will not be saved");
+                Settings::default()
+            }
+        };
+
+        Self { path, settings }
+    }
+
+    pub fn snapshot(&self) -> SettingsSnapshot {
+        SettingsSnapshot {
+            settings: self.settings.clone(),
+            env_overrides: env_overrides(|key| std::env::var(key).ok()),
+        }
+    }
+
+    /// Stored settings with environment overrides applied.
+    pub fn effective(&self) -> Settings {
+        with_env_overrides(&self.settings)
+    }
+
+    pub fn update(&mut self, mut settings: Settings) -> Result<()> {
+        settings.version = SETTINGS_VERSION;
+        settings.validate()?;
+
+        if let Some(path) = &self.path {
+            write_settings(path, &settings)?;
+        }
+
+        self.settings = settings;
+        Ok(())
+    }
+}
+
+pub fn with_env_overrides(settings: &Settings) -> Settings {
+    let mut settings = settings.clone();
+    for EnvOverride { variable, value } in env_overrides(|key| std::env::var(key).ok()) {
+        apply_override(&mut settings, &variable, value);
+    }
+    settings
+}
+
+fn read_settings(path: &Path) -> Result<Settings> {
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    let mut document: Value = serde_json::from_str(&contents)?;
+    migrate(&mut document, MIGRATIONS)?;
+    let settings: Settings = serde_json::from_value(document)?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+fn write_settings(path: &Path, settings: &Settings) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Write to a temp file first so a crash can't leave half a settings file
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_string_pretty(settings)?)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn migrate(document: &mut Value, migrations: &[Migration]) -> Result<()> {
+    let latest = migrations.len() as u64 + 1;
+    let mut version = document.get("version").and_then(Value::as_u64).unwrap_or(1);
+
+    if version > latest {
+        anyhow::bail!("Settings version {} is newer than this build supports ({})", version, latest);
+    }
+
+    while version < latest {
+        migrations[version as usize - 1](document)
+            .with_context(|| format!("Failed to migrate settings from version {}", version))?;
+        version += 1;
+        document["version"] = Value::from(version);
+    }
+
+    Ok(())
+}
+
+fn env_overrides(lookup: impl Fn(&str) -> Option<String>) -> Vec<EnvOverride> {
+    ["OLLAMA_BASE_URL", "LMSTUDIO_BASE_URL", "DEFAULT_PROVIDER", "DEFAULT_MODEL"]
+        .into_iter()
+        .filter_map(|variable| {
+            lookup(variable)
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| EnvOverride { variable: variable.to_string(), value })
+        })
+        .collect()
+}
+
+fn apply_override(settings: &mut Settings, variable: &str, value: String) {
+    match variable {
+        "OLLAMA_BASE_URL" => set_provider_url(settings, "ollama", LLMProvider::Ollama, value),
+        "LMSTUDIO_BASE_URL" => set_provider_url(settings, "lmstudio", LLMProvider::LMStudio, value),
+        "DEFAULT_PROVIDER" => settings.default_provider = Some(value),
+        "DEFAULT_MODEL" => settings.default_model = Some(value),
+        _ => {}
+    }
+}
+
+fn set_provider_url(settings: &mut Settings, id: &str, provider: LLMProvider, base_url: String) {
+    match settings.providers.iter_mut().find(|p| p.id == id) {
+        Some(config) => config.base_url = base_url,
+        None => settings.providers.push(ProviderConfig::new(id, provider, &base_url)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_run_in_order() {
+        fn rename_url(doc: &mut Value) -> Result<()> {
+            let url = doc["ollama_url"].take();
+            doc["providers"] = serde_json::json!([{ "id": "ollama", "provider": "Ollama", "base_url": url }]);
+            Ok(())
+        }
+        fn add_default_model(doc: &mut Value) -> Result<()> {
+            doc["default_model"] = Value::from("codellama:13b-instruct");
+            Ok(())
+        }
+
+        let mut document = serde_json::json!({ "ollama_url": "http://gpu-box:11434" });
+        migrate(&mut document, &[rename_url, add_default_model]).unwrap();
+
+        assert_eq!(document["version"], 3);
+        assert_eq!(document["providers"][0]["base_url"], "http://gpu-box:11434");
+        assert_eq!(document["default_model"], "codellama:13b-instruct");
+    }
+
+    #[test]
+    fn test_newer_settings_are_rejected() {
+        let mut document = serde_json::json!({ "version": SETTINGS_VERSION + 1 });
+        assert!(migrate(&mut document, MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_replace_stored_values() {
+        let mut settings = Settings::default();
+        let overrides = env_overrides(|key| match key {
+            "OLLAMA_BASE_URL" => Some("http://10.0.0.5:11434".to_string()),
+            "DEFAULT_MODEL" => Some("qwen2.5-coder:7b".to_string()),
+            _ => None,
+        });
+        for EnvOverride { variable, value } in overrides {
+            apply_override(&mut settings, &variable, value);
+        }
+
+        assert_eq!(settings.providers[0].base_url, "http://10.0.0.5:11434");
+        assert_eq!(settings.default_model.as_deref(), Some("qwen2.5-coder:7b"));
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("crystalforge-settings-{}", std::process::id()))
+            .join(SETTINGS_FILE);
+
+        let settings = Settings {
+            default_provider: Some("lmstudio".to_string()),
+            ..Settings::default()
+        };
+        write_settings(&path, &settings).unwrap();
+
+        assert_eq!(read_settings(&path).unwrap(), settings);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_settings_are_backed_up_before_saving() {
+        let dir = std::env::temp_dir().join(format!("crystalforge-settings-backup-{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let mut store = SettingsStore::load_from(Some(path.clone()));
+        assert_eq!(store.settings, Settings::default());
+        store.update(Settings::default()).unwrap();
+
+        assert_eq!(std::fs::read_to_string(dir.join(BACKUP_FILE)).unwrap(), "{ not json");
+        assert_eq!(read_settings(&path).unwrap(), Settings::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validation_rejects_duplicate_ids() {
+        let mut settings = Settings::default();
+        settings.providers[1].id = "ollama".to_string();
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use directories::BaseDirs;
+use std::path::PathBuf;
+
+// Matches the identifier in tauri.conf.json so our files sit next to the
+// ones Tauri and the fs plugin create
+pub const APP_IDENTIFIER: &str = "com.lucien.crystalforge";
+
+pub fn app_config_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.config_dir().join(APP_IDENTIFIER))
+}
+
+pub fn app_data_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.data_dir().join(APP_IDENTIFIER))
+}
+
+pub fn app_cache_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.cache_dir().join(APP_IDENTIFIER))
+}
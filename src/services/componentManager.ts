@@ -4,6 +4,7 @@
  */
 
 import { BaseDirectory, exists, create, writeTextFile, readTextFile } from '@tauri-apps/plugin-fs';
+import { invoke } from '@tauri-apps/api/core';
 
 export interface SavedComponent {
   id: string;
@@ -16,6 +17,14 @@ export interface SavedComponent {
   crystal: string;
 }
 
+export interface ComponentMatch {
+  component: SavedComponent;
+  score: number;
+  // Similarity to the query; null when only keywords were matched
+  semantic_score: number | null;
+  keyword_score: number;
+}
+
 export class ComponentManager {
   private readonly COMPONENTS_DIR = 'crystal-forge-components';
   private readonly MANIFEST_FILE = 'components-manifest.json';
@@ -73,6 +82,7 @@ export class ComponentManager {
     
     // Update manifest
     await this.updateManifest(component);
+    this.updateSearchIndex();
     
     return component;
   }
@@ -126,6 +136,7 @@ export class ComponentManager {
       // Update manifest
       const updatedComponents = components.filter(c => c.id !== id);
       await this.saveManifest(updatedComponents);
+      this.updateSearchIndex();
       
       return true;
     } catch (error) {
@@ -167,6 +178,16 @@ export class ComponentManager {
     await this.saveManifest(components);
   }
   
+  /**
+   * Re-embed changed components for semantic search in the background.
+   * Fails harmlessly when no embedding model is configured.
+   */
+  private updateSearchIndex(): void {
+    invoke('update_component_index').catch(error => {
+      console.debug('Component search index not updated:', error);
+    });
+  }
+  
   /**
    * Save the manifest file
    */
@@ -200,9 +221,17 @@ export class ComponentManager {
   }
   
   /**
-   * Search components by name or description
+   * Search components by meaning and keywords, best match first. Falls back
+   * to plain substring matching if the backend search fails.
    */
   async searchComponents(query: string): Promise<SavedComponent[]> {
+    try {
+      const matches = await invoke<ComponentMatch[]>('search_components', { query });
+      return matches.map(m => m.component);
+    } catch (error) {
+      console.error('Component search failed, matching locally:', error);
+    }
+    
     const components = await this.loadComponents();
     const lowerQuery = query.toLowerCase();
     
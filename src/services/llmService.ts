@@ -1,57 +1,322 @@
 import { invoke } from "@tauri-apps/api/core";
 
 // Types matching Rust backend
-export interface ServerStatus {
-  ollama: ServerConnectionStatus;
-  lmstudio: ServerConnectionStatus;
-}
+export type LLMProvider = "Ollama" | "LMStudio" | "OpenAI";
+
+// Keyed by provider id, e.g. "ollama", "lmstudio"
+export type ServerStatus = Record<string, ServerConnectionStatus>;
 
 export interface ServerConnectionStatus {
+  provider: LLMProvider;
+  base_url: string;
   connected: boolean;
   version: string | null;
+  capabilities: ServerCapabilities;
   models_loaded: string[];
+  latency_ms: number | null;
   error: string | null;
+  hint: string | null;
+  circuit: CircuitState;
+}
+
+export interface ServerCapabilities {
+  streaming: boolean;
+  chat: boolean;
+  embeddings: boolean;
+  structured_output: boolean;
+  tool_calling: boolean;
+  vision: boolean;
+}
+
+export type CircuitState = 'closed' | 'open' | 'half_open';
+
+export interface RetryPolicy {
+  max_attempts: number;
+  initial_backoff_ms: number;
+  max_backoff_ms: number;
+  multiplier: number;
+  jitter: number;
+}
+
+export interface CircuitBreakerConfig {
+  failure_threshold: number;
+  cooldown_ms: number;
+}
+
+export interface ProviderConfig {
+  id: string;
+  provider: LLMProvider;
+  base_url: string;
+  api_key?: string | null;
+  api_key_header?: string | null;
+  organization?: string | null;
+}
+
+export interface ProviderInfo {
+  id: string;
+  provider: LLMProvider;
+  base_url: string;
+}
+
+export interface Settings {
+  version: number;
+  providers: ProviderConfig[];
+  default_provider: string | null;
+  default_model: string | null;
+  // Embedding model for semantic component search; set both or neither
+  embedding_provider: string | null;
+  embedding_model: string | null;
+  fallback_chain: string[];
+  retry: RetryPolicy;
+  circuit_breaker: CircuitBreakerConfig;
+  response_cache_max_mb: number;
+  scheduler: SchedulerConfig;
+}
+
+// Generations each provider runs at once; the rest wait in a queue
+export interface SchedulerConfig {
+  default_concurrency: number;
+  provider_concurrency: Record<string, number>; // by provider id
+}
+
+export interface SettingsSnapshot {
+  settings: Settings;
+  env_overrides: { variable: string; value: string }[];
 }
 
 export interface ModelInfo {
   id: string;
   name: string;
   size: number | null;
-  provider: "Ollama" | "LMStudio" | "OpenAI";
+  provider: LLMProvider;
+  provider_id: string;
   status: "Loaded" | "NotLoaded" | "Downloading" | { Error: string };
   performance: PerformanceMetrics | null;
   context_length: number | null;
   quantization: string | null;
+  digest: string | null;
+  modified_at: string | null;
+  family: string | null;
+  parameter_count: number | null;
+  template: string | null;
+  capabilities: string[];
 }
 
 export interface PerformanceMetrics {
   tokens_per_second: number;
-  time_to_first_token: number | null;
-  memory_usage: number | null;
+  time_to_first_token: number | null; // ms
+  prompt_tokens_per_second: number | null;
+  memory_usage: number | null; // peak bytes of the server process
   last_updated: string;
 }
 
+export interface BenchmarkResult {
+  model: string;
+  provider_id: string;
+  digest: string | null;
+  hardware_profile: string;
+  suite_version: number;
+  prompts: {
+    name: string;
+    time_to_first_token_ms: number;
+    tokens_generated: number;
+    tokens_per_second: number;
+    prompt_tokens_per_second: number | null;
+  }[];
+  metrics: PerformanceMetrics;
+}
+
 export interface GenerateResponse {
   text: string;
   model: string;
+  provider_id: string;
   tokens_generated: number;
   generation_time_ms: number;
   tokens_per_second: number;
+  timings: GenerationTimings;
+  usage: TokenUsage;
+  finish_reason: FinishReason;
+  cache_hit: boolean; // served from the response cache
+}
+
+export interface GenerationTimings {
+  total_ms: number | null;
+  load_ms: number | null;
+  prompt_eval_ms: number | null;
+  prompt_eval_count: number | null;
+  eval_ms: number | null;
+  time_to_first_token_ms: number | null;
+  provider_reported: boolean;
 }
 
 export interface ChatResponse {
   message: Message;
   model: string;
+  provider_id: string;
   tokens_generated: number;
   generation_time_ms: number;
   tokens_per_second: number;
+  timings: GenerationTimings;
+  usage: TokenUsage;
+  finish_reason: FinishReason;
+  cache_hit: boolean; // served from the response cache
+}
+
+export type FinishReason = "stop" | "length" | "tool_calls" | "content_filter" | "unknown";
+
+// prompt_tokens is 0 when the provider doesn't report it
+export interface TokenUsage {
+  prompt_tokens: number;
+  completion_tokens: number;
+  total_tokens: number;
+}
+
+export interface UsageTotals {
+  requests: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  total_tokens: number;
+}
+
+export interface UsageReport {
+  session: UsageTotals; // since the app started
+  models: (UsageTotals & { provider_id: string; model: string })[];
 }
 
 export interface Message {
-  role: "system" | "user" | "assistant";
+  role: "system" | "user" | "assistant" | "tool";
   content: string;
+  // Base64-encoded images, e.g. from encodeImage(); only vision models
+  // receive messages with images
+  images?: string[];
+  tool_calls?: ToolCall[];
+  tool_call_id?: string; // on "tool" messages, the call they answer
+}
+
+export interface ToolDefinition {
+  name: string;
+  description: string;
+  parameters: Record<string, unknown>; // JSON Schema of the arguments
+}
+
+export interface ToolCall {
+  id: string;
+  name: string;
+  arguments: unknown;
+}
+
+// Payload of "llm-tool-call" events, sent as each tool call completes
+export interface ToolCallEvent {
+  request_id: string;
+  call: ToolCall;
+  result: string;
+}
+
+// Payload of "llm-queue" events; position is 1 for the next job to start
+// and 0 once the job is running
+export interface QueueEvent {
+  request_id: string;
+  provider_id: string;
+  position: number;
+}
+
+export interface QueueStatus {
+  running: number;
+  waiting: number;
+}
+
+// Interactive jobs start before queued background ones
+export type Priority = "interactive" | "background";
+
+// Unset parameters use the provider's defaults. Providers that can't honour
+// a parameter are skipped when falling back.
+export interface SamplingOptions {
+  temperature?: number;
+  max_tokens?: number;
+  top_p?: number;
+  top_k?: number;
+  min_p?: number;
+  stop?: string[];
+  seed?: number;
+  repeat_penalty?: number;
+  frequency_penalty?: number;
+  presence_penalty?: number;
+  num_ctx?: number; // Ollama only
+}
+
+// Options shared by generate/chat; tokens arrive as "llm-token" events
+// keyed by requestId when streaming
+export interface GenerationOptions {
+  sampling?: SamplingOptions;
+  // JSON Schema the output must match; the text is then compact JSON
+  schema?: Record<string, unknown>;
+  stream?: boolean;
+  // Continue output cut off by the token limit and stitch the parts together
+  autoContinue?: boolean;
+  // Names of tools from listTools() the model may call (chat only, not with
+  // schema or autoContinue); the backend runs them and returns the final reply
+  tools?: string[];
+  // Answer identical earlier requests from the on-disk cache; best with
+  // temperature 0 or a fixed seed. Ignored with tools or autoContinue.
+  cache?: boolean;
+  priority?: Priority;
+  // Queued jobs take turns between crystals
+  crystal?: string;
+  requestId?: string;
+}
+
+export interface CacheStats {
+  entries: number;
+  bytes: number;
+  max_bytes: number;
+}
+
+// Embedding models can't generate text and are listed separately
+export type ModelKind = "generation" | "embedding";
+
+export interface EmbeddingResponse {
+  model: string;
+  provider_id: string;
+  embeddings: number[][]; // in input order
+  dimensions: number;
+  usage: TokenUsage;
+}
+
+export interface RunningModel {
+  model: string;
+  provider_id: string;
+  size: number | null;
+  size_vram: number | null;
+  expires_at: string | null;
+  context_length: number | null;
+}
+
+// Payload of "llm-pull-progress" events
+export interface PullProgressEvent {
+  request_id: string;
+  model: string;
+  status: string;
+  digest: string | null;
+  total: number | null;
+  completed: number | null;
+  model_status: ModelInfo["status"];
 }
 
+export type GenerationError =
+  | { kind: "connection_refused"; url: string; message: string }
+  | { kind: "timeout"; message: string }
+  | { kind: "model_not_found"; model: string; message: string }
+  | { kind: "http_status"; context: string; status: number; body: string }
+  | { kind: "decode"; message: string }
+  | { kind: "provider"; message: string }
+  | { kind: "context_overflow"; message: string }
+  | { kind: "unavailable"; message: string }
+  | { kind: "unsupported"; message: string }
+  | { kind: "cancelled"; request_id: string }
+  | { kind: "validation"; message: string }
+  | { kind: "schema_mismatch"; message: string; output: string }
+  | { kind: "internal"; message: string };
+
 class LLMService {
   // Detect all LLM servers
   async detectServers(): Promise<ServerStatus> {
@@ -59,27 +324,14 @@ class LLMService {
       return await invoke<ServerStatus>("detect_llm_servers");
     } catch (error) {
       console.error("Failed to detect LLM servers:", error);
-      return {
-        ollama: {
-          connected: false,
-          version: null,
-          models_loaded: [],
-          error: "Failed to connect to Ollama"
-        },
-        lmstudio: {
-          connected: false,
-          version: null,
-          models_loaded: [],
-          error: "Failed to connect to LM Studio"
-        }
-      };
+      return {};
     }
   }
 
-  // List all available models
-  async listModels(): Promise<ModelInfo[]> {
+  // List all available models of a kind (text generation by default)
+  async listModels(kind: ModelKind = "generation"): Promise<ModelInfo[]> {
     try {
-      return await invoke<ModelInfo[]>("list_available_models");
+      return await invoke<ModelInfo[]>("list_available_models", { kind });
     } catch (error) {
       console.error("Failed to list models:", error);
       return [];
@@ -91,13 +343,15 @@ class LLMService {
     provider: string,
     model: string,
     prompt: string,
-    temperature?: number
+    temperature?: number,
+    options: GenerationOptions = {}
   ): Promise<GenerateResponse> {
     return await invoke<GenerateResponse>("generate_code", {
       provider,
       model,
       prompt,
-      temperature
+      temperature,
+      ...options
     });
   }
 
@@ -106,16 +360,85 @@ class LLMService {
     provider: string,
     model: string,
     messages: Message[],
-    temperature?: number
+    temperature?: number,
+    options: GenerationOptions = {}
   ): Promise<ChatResponse> {
     return await invoke<ChatResponse>("chat_with_model", {
       provider,
       model,
       messages,
-      temperature
+      temperature,
+      ...options
     });
   }
 
+  // Embed texts with one embedding model; there's no fallback, since
+  // vectors from different models can't be compared
+  async embed(provider: string, model: string, inputs: string[]): Promise<EmbeddingResponse> {
+    return await invoke<EmbeddingResponse>("embed_texts", { provider, model, inputs });
+  }
+
+  // Abort a running generation; resolves false if it already finished
+  async cancelGeneration(requestId: string): Promise<boolean> {
+    return await invoke<boolean>("cancel_generation", { requestId });
+  }
+
+  // Generations running and queued, by provider id
+  async getQueueStatus(): Promise<Record<string, QueueStatus>> {
+    return await invoke<Record<string, QueueStatus>>("get_queue_status");
+  }
+
+  // Download a model (Ollama only); progress arrives as "llm-pull-progress"
+  // events and cancelGeneration(requestId) aborts it
+  async pullModel(model: string, options: { provider?: string; requestId?: string } = {}): Promise<void> {
+    await invoke("pull_model", { model, ...options });
+  }
+
+  // Run the benchmark suite; results also show up as ModelInfo.performance
+  async benchmarkModel(provider: string, model: string, requestId?: string): Promise<BenchmarkResult> {
+    return await invoke<BenchmarkResult>("benchmark_model", { provider, model, requestId });
+  }
+
+  // Model lifecycle; unsupported operations reject with kind "unsupported"
+  async deleteModel(provider: string, model: string): Promise<void> {
+    await invoke("delete_model", { provider, model });
+  }
+
+  async loadModel(provider: string, model: string, keepAliveSecs?: number): Promise<void> {
+    await invoke("load_model", { provider, model, keepAliveSecs });
+  }
+
+  async unloadModel(provider: string, model: string): Promise<void> {
+    await invoke("unload_model", { provider, model });
+  }
+
+  async listRunningModels(): Promise<RunningModel[]> {
+    return await invoke<RunningModel[]>("list_running_models");
+  }
+
+  // Cumulative token usage for this session, per model
+  async getTokenUsage(): Promise<UsageReport> {
+    return await invoke<UsageReport>("get_token_usage");
+  }
+
+  async getCacheStats(): Promise<CacheStats> {
+    return await invoke<CacheStats>("get_cache_stats");
+  }
+
+  async clearResponseCache(): Promise<void> {
+    await invoke("clear_response_cache");
+  }
+
+  // Read, downsize and base64-encode a local image for Message.images
+  async encodeImage(path: string): Promise<string> {
+    return await invoke<string>("encode_image", { path });
+  }
+
+  // Tools the backend can run for a model during chat
+  async listTools(): Promise<ToolDefinition[]> {
+    return await invoke<ToolDefinition[]>("list_tools");
+  }
+
   // Create a React component using the model
   async generateReactComponent(
     model: string,
@@ -137,6 +460,28 @@ class LLMService {
     return response.message.content;
   }
 
+  // Provider registry management
+  async listProviders(): Promise<ProviderInfo[]> {
+    return await invoke<ProviderInfo[]>("list_llm_providers");
+  }
+
+  async addProvider(config: ProviderConfig): Promise<void> {
+    await invoke("add_llm_provider", { config });
+  }
+
+  async removeProvider(id: string): Promise<boolean> {
+    return await invoke<boolean>("remove_llm_provider", { id });
+  }
+
+  // Settings as stored on disk plus active environment overrides
+  async getSettings(): Promise<SettingsSnapshot> {
+    return await invoke<SettingsSnapshot>("get_settings");
+  }
+
+  async updateSettings(settings: Settings): Promise<SettingsSnapshot> {
+    return await invoke<SettingsSnapshot>("update_settings", { settings });
+  }
+
   // Get optimal model for hardware
   async getOptimalModel(hardware: any): Promise<string> {
     return await invoke<string>("get_optimal_model", { hardware });
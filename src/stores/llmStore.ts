@@ -58,13 +58,13 @@ export const useLLMStore = create<LLMState>()(
           const status = await llmService.detectServers();
           set({
             serverStatus: status,
-            ollamaConnected: status.ollama.connected,
-            lmstudioConnected: status.lmstudio.connected,
+            ollamaConnected: status.ollama?.connected ?? false,
+            lmstudioConnected: status.lmstudio?.connected ?? false,
             isLoadingServers: false
           });
           
           // Auto-load models if servers are connected
-          if (status.ollama.connected || status.lmstudio.connected) {
+          if (Object.values(status).some((server) => server.connected)) {
             await get().loadModels();
           }
         } catch (error) {